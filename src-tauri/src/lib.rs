@@ -1,35 +1,213 @@
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
 use std::fs;
-use std::path::PathBuf;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Mutex;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tauri::{
-    menu::{Menu, MenuItem},
-    AppHandle, Manager, WebviewUrl, WebviewWindowBuilder, PhysicalPosition,
+    menu::{CheckMenuItem, Menu, MenuItem},
+    AppHandle, Emitter, LogicalSize, Manager, Monitor, WebviewUrl, WebviewWindow, WebviewWindowBuilder, PhysicalPosition,
+    PhysicalSize,
 };
+use tauri_plugin_autostart::{MacosLauncher, ManagerExt};
 use tauri_plugin_global_shortcut::{Code, GlobalShortcutExt, Modifiers, Shortcut, ShortcutState};
+use tauri_plugin_store::StoreExt;
 
 const WINDOW_LABEL: &str = "main";
+const TRAY_ID: &str = "main";
 const WINDOW_WIDTH: f64 = 280.0;
 const WINDOW_HEIGHT: f64 = 300.0;
+// 窗口尺寸上限，防止配置异常导致窗口过大
+const MAX_WINDOW_SIZE: f64 = 4096.0;
 const CONFIG_FILE: &str = "config.json";
+// 当前配置文件版本，字段含义变化时递增并在 migrate_config 中补充迁移
+const CONFIG_VERSION: u32 = 1;
+// 心跳文件，与 config.json 同目录
+const HEARTBEAT_FILE: &str = "heartbeat.json";
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+// 超过该时间的心跳视为过期遗留，不再判定为崩溃
+const HEARTBEAT_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+// API Key 单独存放在 store 中，不写入 config.json
+const SECRETS_STORE: &str = "secrets.json";
+const API_KEY_ENTRY: &str = "api_key";
+const HOVER_EXPAND_WIDTH: f64 = 360.0;
+const HOVER_EXPAND_HEIGHT: f64 = 420.0;
+// 圆点模式下的窗口尺寸
+const DOT_SIZE: f64 = 24.0;
+// 悬停状态变化后的防抖时间，避免鼠标划过时窗口闪烁
+const HOVER_DEBOUNCE: Duration = Duration::from_millis(150);
+
+// 显示器可用区域（逻辑像素）低于此尺寸时视为小屏幕，如 1024×600 的上网本
+// 1920×1080 缩放 150% 时可用区域约 1280×690，不应算作小屏幕
+const SMALL_SCREEN_MAX_WIDTH: f64 = 1024.0;
+const SMALL_SCREEN_MAX_HEIGHT: f64 = 600.0;
+
+// 无边框窗口缩放边框宽度范围（逻辑像素）
+const MIN_RESIZE_BORDER: u32 = 1;
+const MAX_RESIZE_BORDER: u32 = 16;
+// 后台写入线程检查待写配置的间隔
+const CONFIG_FLUSH_TICK: Duration = Duration::from_millis(100);
+// 拖动或缩放停止多久后视为结束，结束前不写入窗口位置和尺寸
+const MOVE_SETTLE: Duration = Duration::from_millis(300);
+// 程序主动移动窗口的位置最多记录的条数
+const PROGRAMMATIC_MOVES_CAP: usize = 8;
+// 面板切换记录最多保留的条数
+const TOGGLE_HISTORY_CAP: usize = 50;
+// 开机自启时附带的启动参数
+const AUTOSTART_ARG: &str = "--autostart";
+// 后端触发的两次刷新之间的最小间隔
+const REFRESH_MIN_INTERVAL: Duration = Duration::from_secs(2);
 
 // 配置结构体
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 struct AppConfig {
+    // 配置文件版本
+    version: u32,
     shortcut_modifiers: Vec<String>,
     shortcut_key: String,
     window_width: f64,
     window_height: f64,
+    // 打开设置页的快捷键，按键为空表示未启用
+    settings_shortcut_modifiers: Vec<String>,
+    settings_shortcut_key: String,
+    // 按住时显示面板的速览快捷键，按键为空表示未启用
+    peek_shortcut_modifiers: Vec<String>,
+    peek_shortcut_key: String,
+    // 切换置顶的快捷键，按键为空表示未启用
+    pin_shortcut_modifiers: Vec<String>,
+    pin_shortcut_key: String,
+    // 立即刷新行情的快捷键，按键为空表示未启用
+    refresh_shortcut_modifiers: Vec<String>,
+    refresh_shortcut_key: String,
+    // 快捷键允许的最多修饰键数量，None 表示不限制
+    max_modifiers: Option<usize>,
+    // 是否在鼠标所在的显示器上显示面板，关闭时始终使用主显示器
+    show_on_active_monitor: bool,
+    // 置顶最长持续时间（秒），0 表示不限制
+    max_pin_duration_secs: u64,
+    // 时间显示格式："24h" 或 "12h"
+    time_format: String,
+    // 鼠标悬停时是否展开面板
+    expand_on_hover: bool,
+    hover_expand_width: f64,
+    hover_expand_height: f64,
+    // 数字格式化使用的区域设置，如 "zh-CN"、"de-DE"
+    locale: String,
+    // 上次使用的布局模式："compact" 普通面板，"dot" 圆点模式，启动时恢复
+    last_layout_mode: String,
+    // 面板显示期间是否注册全局 Esc 隐藏面板
+    escape_to_hide: bool,
+    // 无边框窗口的拖动区域："whole" 整个窗口，"header" 仅标题栏
+    drag_region: String,
+    // 隐藏面板时是否把焦点还给之前的应用
+    restore_prev_focus: bool,
+    // 两次写入配置文件的最小间隔（毫秒），用于合并频繁写入
+    min_write_interval_ms: u64,
+    // 面板显示时是否刷新行情
+    refresh_on_show: bool,
+    // 面板显示时是否出现在任务栏（隐藏时始终不出现）
+    taskbar_when_visible: bool,
+    // 已保存的窗口位置巡回，按名称存储
+    tours: HashMap<String, Vec<Geometry>>,
+    // 显示面板后延迟多久再置顶（毫秒），0 表示立即置顶
+    always_on_top_delay_ms: u64,
+    // 置顶状态，重启后保持
+    pinned: bool,
+    // 用户拖动后保存的窗口位置（物理像素），未保存时按锚点定位
+    x: Option<i32>,
+    y: Option<i32>,
+    // 拖动后的位置是否保存到配置；关闭时只在本次运行中使用，下次启动回到锚点
+    persist_manual_position: bool,
+    // 用户设定的“原位”几何信息
+    home: Option<Geometry>,
+    // 面板锚定位置：top-left、top-right、bottom-left、bottom-right、center
+    anchor: String,
+    // 窗口不透明度，范围 0.1 ~ 1.0
+    opacity: f64,
+    // 夜间时段的不透明度上限，None 表示不启用；时段为本地时间 "HH:MM"，可跨午夜
+    night_opacity: Option<f64>,
+    night_start: String,
+    night_end: String,
+    // 是否开机自启
+    autostart: bool,
+    // 无边框窗口可拖动缩放的边框宽度（逻辑像素），由前端绘制
+    resize_border_px: u32,
+    // 跨缩放比例不同的显示器移动时是否保持逻辑尺寸不变
+    track_dpi: bool,
+    // 小屏幕上使用的面板尺寸
+    small_screen_width: f64,
+    small_screen_height: f64,
+    // 交互模式："hotkey" 快捷键呼出并失焦隐藏，"tray" 仅通过托盘打开
+    interaction_model: String,
+    // 鼠标穿透：面板仍显示，但点击会传递到下方窗口
+    click_through: bool,
+    // 关闭最后一个窗口时是否退出应用；macOS 习惯保持运行，其他平台默认退出
+    exit_on_last_window_close: bool,
+}
+
+// 窗口几何信息：位置为物理像素，尺寸为逻辑像素（与 window_width/window_height 一致）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Geometry {
+    x: i32,
+    y: i32,
+    width: f64,
+    height: f64,
 }
 
 impl Default for AppConfig {
     fn default() -> Self {
         Self {
+            version: CONFIG_VERSION,
             shortcut_modifiers: vec!["Alt".to_string()],
             shortcut_key: "M".to_string(),
             window_width: WINDOW_WIDTH,
             window_height: WINDOW_HEIGHT,
+            settings_shortcut_modifiers: Vec::new(),
+            settings_shortcut_key: String::new(),
+            peek_shortcut_modifiers: Vec::new(),
+            peek_shortcut_key: String::new(),
+            pin_shortcut_modifiers: Vec::new(),
+            pin_shortcut_key: String::new(),
+            refresh_shortcut_modifiers: Vec::new(),
+            refresh_shortcut_key: String::new(),
+            max_modifiers: None,
+            show_on_active_monitor: true,
+            max_pin_duration_secs: 0,
+            time_format: "24h".to_string(),
+            expand_on_hover: false,
+            hover_expand_width: HOVER_EXPAND_WIDTH,
+            hover_expand_height: HOVER_EXPAND_HEIGHT,
+            locale: "zh-CN".to_string(),
+            last_layout_mode: "compact".to_string(),
+            escape_to_hide: false,
+            drag_region: "header".to_string(),
+            restore_prev_focus: false,
+            min_write_interval_ms: 1000,
+            refresh_on_show: false,
+            taskbar_when_visible: false,
+            tours: HashMap::new(),
+            always_on_top_delay_ms: 0,
+            pinned: false,
+            x: None,
+            y: None,
+            persist_manual_position: true,
+            home: None,
+            anchor: "top-right".to_string(),
+            opacity: 1.0,
+            night_opacity: None,
+            night_start: "22:00".to_string(),
+            night_end: "07:00".to_string(),
+            autostart: false,
+            resize_border_px: 4,
+            track_dpi: true,
+            small_screen_width: 240.0,
+            small_screen_height: 260.0,
+            interaction_model: "hotkey".to_string(),
+            click_through: false,
+            exit_on_last_window_close: cfg!(not(target_os = "macos")),
         }
     }
 }
@@ -37,9 +215,111 @@ impl Default for AppConfig {
 // 全局置顶状态
 static PINNED: AtomicBool = AtomicBool::new(false);
 
+// 自动取消置顶的定时器，发送新的时长重新计时，发送 None 取消
+static AUTO_UNPIN_TIMER: Mutex<Option<Sender<Option<Duration>>>> = Mutex::new(None);
+
+// 悬停展开状态，展开期间的尺寸变化不写入配置
+static HOVER_EXPANDED: AtomicBool = AtomicBool::new(false);
+
+// 圆点模式状态，期间的尺寸变化不写入配置
+static DOT_MODE: AtomicBool = AtomicBool::new(false);
+
+// 是否正在使用小屏幕尺寸，期间的尺寸变化不写入配置
+static SMALL_SCREEN: AtomicBool = AtomicBool::new(false);
+
+// 悬停事件代数，用于防抖
+static HOVER_GENERATION: AtomicU64 = AtomicU64::new(0);
+
 // 当前快捷键配置 (modifiers, key)
 static CURRENT_SHORTCUT: Mutex<Option<(Vec<String>, String)>> = Mutex::new(None);
 
+// 获取当前快捷键的锁；持有者 panic 导致锁中毒时仍取回其中的数据，避免后续调用全部失败
+fn lock_current_shortcut() -> MutexGuard<'static, Option<(Vec<String>, String)>> {
+    CURRENT_SHORTCUT.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+// 快捷键触发的动作
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ShortcutAction {
+    Toggle,
+    Settings,
+    Peek,
+    Pin,
+    Refresh,
+    Hide,
+    EmergencyReset,
+}
+
+// 已注册的快捷键及其动作，全局快捷键回调按此分发
+static SHORTCUT_REGISTRY: Mutex<Vec<(Shortcut, ShortcutAction)>> = Mutex::new(Vec::new());
+
+// 快捷键事件的分发线程；插件回调期间持有其内部锁，在回调中注册或注销快捷键会死锁，
+// 因此回调只把事件交给分发线程按顺序处理
+static SHORTCUT_EVENTS: Mutex<Option<Sender<(ShortcutAction, ShortcutState)>>> = Mutex::new(None);
+
+// 失焦时是否自动隐藏面板，由交互模式决定
+static AUTO_HIDE: AtomicBool = AtomicBool::new(true);
+
+// 鼠标穿透状态
+static CLICK_THROUGH: AtomicBool = AtomicBool::new(false);
+
+// 是否正在速览（按住速览快捷键期间不因失焦隐藏）
+static PEEKING: AtomicBool = AtomicBool::new(false);
+
+// 面板是否由本次速览打开，松开速览快捷键时只隐藏由速览打开的面板
+static PEEK_OPENED: AtomicBool = AtomicBool::new(false);
+
+// 本次是否由开机自启拉起
+static LAUNCHED_AT_LOGIN: AtomicBool = AtomicBool::new(false);
+
+// 托盘图标是否创建成功
+static TRAY_AVAILABLE: AtomicBool = AtomicBool::new(false);
+
+// 展开详情气泡前的窗口宽度，None 表示未展开
+static BUBBLE_BASE_WIDTH: Mutex<Option<f64>> = Mutex::new(None);
+
+// 程序主动设置的窗口位置，尚未收到对应的 Moved 事件
+static PROGRAMMATIC_MOVES: Mutex<VecDeque<(i32, i32)>> = Mutex::new(VecDeque::new());
+
+// 最近的面板切换记录，最新的在队尾
+static TOGGLE_HISTORY: Mutex<VecDeque<ToggleEvent>> = Mutex::new(VecDeque::new());
+
+// 各巡回下一步的序号
+static TOUR_STEPS: Mutex<Option<HashMap<String, usize>>> = Mutex::new(None);
+
+// 启动报告
+static STARTUP_REPORT: Mutex<Option<StartupReport>> = Mutex::new(None);
+
+// 上次运行是否异常退出
+static PREVIOUS_SESSION_CRASHED: AtomicBool = AtomicBool::new(false);
+
+// 心跳线程是否继续写入；写入心跳期间持有此锁，正常退出的标记不会被心跳线程覆盖
+static HEARTBEAT_ACTIVE: Mutex<bool> = Mutex::new(false);
+
+// 内存中的当前配置，首次读取后缓存，修改时同步更新
+static CONFIG_CACHE: ConfigCache = ConfigCache::new(read_config_file);
+
+// 最近一次从磁盘读取配置的耗时（毫秒）
+static CONFIG_LOAD_TIME_MS: AtomicU64 = AtomicU64::new(0);
+
+// 等待写入磁盘的配置，由后台线程按间隔合并写入
+static PENDING_CONFIG: Mutex<Option<AppConfig>> = Mutex::new(None);
+
+// 配置文件写入锁，多个线程共用同一个临时文件，写入和改名期间需互斥
+static CONFIG_WRITE_LOCK: Mutex<()> = Mutex::new(());
+
+// 上次写入配置文件的时间
+static LAST_CONFIG_WRITE: Mutex<Option<Instant>> = Mutex::new(None);
+
+// 上次通知前端刷新行情的时间，用于限制刷新频率
+static LAST_REFRESH_REQUEST: Mutex<Option<Instant>> = Mutex::new(None);
+
+// 上次用户拖动或缩放窗口的时间
+static LAST_USER_MOVE: Mutex<Option<Instant>> = Mutex::new(None);
+
+// 本次运行中拖动后的位置（不保存拖动位置时使用）
+static SESSION_POSITION: Mutex<Option<(i32, i32)>> = Mutex::new(None);
+
 // 配置文件路径
 static CONFIG_PATH: Mutex<Option<PathBuf>> = Mutex::new(None);
 
@@ -48,319 +328,4596 @@ fn get_config_path() -> Option<PathBuf> {
     CONFIG_PATH.lock().ok()?.clone()
 }
 
-// 加载配置
-fn load_config() -> AppConfig {
-    if let Some(path) = get_config_path() {
-        if path.exists() {
-            if let Ok(content) = fs::read_to_string(&path) {
-                if let Ok(config) = serde_json::from_str::<AppConfig>(&content) {
-                    return config;
-                }
-            }
-        }
-    }
-    AppConfig::default()
+// 配置文件的实际路径，供前端展示；路径未初始化时返回 None
+#[tauri::command]
+fn config_path() -> Option<String> {
+    get_config_path().map(|path| path.to_string_lossy().into_owned())
 }
 
-// 保存配置
-fn save_config(config: &AppConfig) {
-    if let Some(path) = get_config_path() {
+// 心跳记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Heartbeat {
+    timestamp: u64,
+    clean_exit: bool,
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn get_heartbeat_path() -> Option<PathBuf> {
+    Some(get_config_path()?.with_file_name(HEARTBEAT_FILE))
+}
+
+fn read_heartbeat() -> Option<String> {
+    fs::read_to_string(get_heartbeat_path()?).ok()
+}
+
+fn write_heartbeat(clean_exit: bool) {
+    if let Some(path) = get_heartbeat_path() {
         if let Some(parent) = path.parent() {
             let _ = fs::create_dir_all(parent);
         }
-        if let Ok(content) = serde_json::to_string_pretty(config) {
+        let heartbeat = Heartbeat {
+            timestamp: unix_now(),
+            clean_exit,
+        };
+        if let Ok(content) = serde_json::to_string(&heartbeat) {
             let _ = fs::write(path, content);
         }
     }
 }
 
+// 判断上次运行是否崩溃：存在较新的心跳且未正常退出
+fn is_probable_crash(heartbeat_age: Option<Duration>, clean_exit: bool) -> bool {
+    match heartbeat_age {
+        Some(age) => !clean_exit && age <= HEARTBEAT_MAX_AGE,
+        None => false,
+    }
+}
+
+// 根据启动时读到的心跳文件内容判断上次运行是否崩溃；文件缺失或无法解析视为没有心跳
+fn previous_run_crashed(content: Option<&str>, now: u64) -> bool {
+    let heartbeat = content.and_then(|content| serde_json::from_str::<Heartbeat>(content).ok());
+    match heartbeat {
+        Some(heartbeat) => {
+            let age = Duration::from_secs(now.saturating_sub(heartbeat.timestamp));
+            is_probable_crash(Some(age), heartbeat.clean_exit)
+        }
+        None => is_probable_crash(None, false),
+    }
+}
+
+// 检查上次运行状态，并开始周期性写入心跳
+fn start_heartbeat() {
+    let crashed = previous_run_crashed(read_heartbeat().as_deref(), unix_now());
+    PREVIOUS_SESSION_CRASHED.store(crashed, Ordering::SeqCst);
+
+    *HEARTBEAT_ACTIVE.lock().unwrap_or_else(PoisonError::into_inner) = true;
+    std::thread::spawn(|| loop {
+        {
+            let active = HEARTBEAT_ACTIVE.lock().unwrap_or_else(PoisonError::into_inner);
+            if !*active {
+                break;
+            }
+            write_heartbeat(false);
+        }
+        std::thread::sleep(HEARTBEAT_INTERVAL);
+    });
+}
+
+// 停止心跳线程并写入正常退出标记，下次启动不再判定为崩溃
+fn stop_heartbeat() {
+    let mut active = HEARTBEAT_ACTIVE.lock().unwrap_or_else(PoisonError::into_inner);
+    *active = false;
+    write_heartbeat(true);
+}
+
 #[tauri::command]
-fn set_pinned(pinned: bool) {
-    PINNED.store(pinned, Ordering::SeqCst);
+fn previous_session_crashed() -> bool {
+    PREVIOUS_SESSION_CRASHED.load(Ordering::SeqCst)
 }
 
+// 前端提示过崩溃恢复后清除标记，避免重复提示
+// 心跳文件在启动时已被本次运行覆盖，下次启动也不会再次判定
 #[tauri::command]
-fn get_pinned() -> bool {
-    PINNED.load(Ordering::SeqCst)
+fn acknowledge_crash() {
+    PREVIOUS_SESSION_CRASHED.store(false, Ordering::SeqCst);
 }
 
-// 解析修饰键
-fn parse_modifiers(mods: &[String]) -> Option<Modifiers> {
-    if mods.is_empty() {
-        return None;
+// 配置缓存：只在首次加载和显式重新加载时读取磁盘，读取函数可替换以便测试
+struct ConfigCache {
+    config: Mutex<Option<AppConfig>>,
+    read: fn(&Path) -> AppConfig,
+}
+
+impl ConfigCache {
+    const fn new(read: fn(&Path) -> AppConfig) -> Self {
+        ConfigCache { config: Mutex::new(None), read }
     }
-    let mut result = Modifiers::empty();
-    for m in mods {
-        match m.to_uppercase().as_str() {
-            "ALT" | "OPTION" => result |= Modifiers::ALT,
-            "CTRL" | "CONTROL" => result |= Modifiers::CONTROL,
-            "SHIFT" => result |= Modifiers::SHIFT,
-            "META" | "COMMAND" | "CMD" | "SUPER" => result |= Modifiers::META,
-            _ => {}
+
+    fn load(&self, path: Option<PathBuf>) -> AppConfig {
+        if let Some(cached) = self.config.lock().ok().and_then(|c| c.clone()) {
+            return cached;
         }
+        // 配置路径初始化前不缓存，避免把默认配置当作已加载
+        let Some(path) = path else {
+            return AppConfig::default();
+        };
+        let config = (self.read)(&path);
+        self.set(&config);
+        config
+    }
+
+    fn set(&self, config: &AppConfig) {
+        if let Ok(mut cache) = self.config.lock() {
+            *cache = Some(config.clone());
+        }
+    }
+
+    fn reload(&self, path: &Path) -> AppConfig {
+        let config = (self.read)(path);
+        self.set(&config);
+        config
     }
-    Some(result)
 }
 
-// 解析按键
-fn parse_key(key: &str) -> Option<Code> {
-    match key.to_uppercase().as_str() {
-        "A" => Some(Code::KeyA),
-        "B" => Some(Code::KeyB),
-        "C" => Some(Code::KeyC),
-        "D" => Some(Code::KeyD),
-        "E" => Some(Code::KeyE),
-        "F" => Some(Code::KeyF),
-        "G" => Some(Code::KeyG),
-        "H" => Some(Code::KeyH),
-        "I" => Some(Code::KeyI),
-        "J" => Some(Code::KeyJ),
-        "K" => Some(Code::KeyK),
-        "L" => Some(Code::KeyL),
-        "M" => Some(Code::KeyM),
-        "N" => Some(Code::KeyN),
-        "O" => Some(Code::KeyO),
-        "P" => Some(Code::KeyP),
-        "Q" => Some(Code::KeyQ),
-        "R" => Some(Code::KeyR),
-        "S" => Some(Code::KeyS),
-        "T" => Some(Code::KeyT),
-        "U" => Some(Code::KeyU),
-        "V" => Some(Code::KeyV),
-        "W" => Some(Code::KeyW),
-        "X" => Some(Code::KeyX),
-        "Y" => Some(Code::KeyY),
-        "Z" => Some(Code::KeyZ),
-        "0" | "DIGIT0" => Some(Code::Digit0),
-        "1" | "DIGIT1" => Some(Code::Digit1),
-        "2" | "DIGIT2" => Some(Code::Digit2),
-        "3" | "DIGIT3" => Some(Code::Digit3),
-        "4" | "DIGIT4" => Some(Code::Digit4),
-        "5" | "DIGIT5" => Some(Code::Digit5),
-        "6" | "DIGIT6" => Some(Code::Digit6),
-        "7" | "DIGIT7" => Some(Code::Digit7),
-        "8" | "DIGIT8" => Some(Code::Digit8),
-        "9" | "DIGIT9" => Some(Code::Digit9),
-        "F1" => Some(Code::F1),
-        "F2" => Some(Code::F2),
-        "F3" => Some(Code::F3),
-        "F4" => Some(Code::F4),
-        "F5" => Some(Code::F5),
-        "F6" => Some(Code::F6),
-        "F7" => Some(Code::F7),
-        "F8" => Some(Code::F8),
-        "F9" => Some(Code::F9),
-        "F10" => Some(Code::F10),
-        "F11" => Some(Code::F11),
-        "F12" => Some(Code::F12),
-        "SPACE" => Some(Code::Space),
-        "ENTER" => Some(Code::Enter),
-        "ESCAPE" | "ESC" => Some(Code::Escape),
-        "TAB" => Some(Code::Tab),
-        _ => None,
+// 加载配置，优先返回缓存（含尚未写入磁盘的修改）
+fn load_config() -> AppConfig {
+    CONFIG_CACHE.load(get_config_path())
+}
+
+fn set_cached_config(config: &AppConfig) {
+    CONFIG_CACHE.set(config);
+}
+
+// 从磁盘读取并解析配置，记录耗时
+fn read_config_file(path: &Path) -> AppConfig {
+    let started = Instant::now();
+    let mut config = AppConfig::default();
+    if path.exists() {
+        if let Ok(content) = fs::read_to_string(path) {
+            let parsed = serde_json::from_str::<serde_json::Value>(&content)
+                .and_then(|value| serde_json::from_value::<AppConfig>(migrate_config(value)));
+            match parsed {
+                Ok(parsed) => {
+                    config = parsed;
+                    clamp_numeric_fields(&mut config);
+                }
+                Err(e) => backup_corrupt_config(path, &e),
+            }
+        }
     }
+    CONFIG_LOAD_TIME_MS.store(started.elapsed().as_millis() as u64, Ordering::SeqCst);
+    config
 }
 
 #[tauri::command]
-fn update_shortcut(app: AppHandle, modifiers: Vec<String>, key: String) -> Result<String, String> {
-    // 解析新快捷键
-    let mods = parse_modifiers(&modifiers);
-    let code = parse_key(&key).ok_or_else(|| format!("无效的按键: {}", key))?;
-    let new_shortcut = Shortcut::new(mods, code);
+fn config_load_time_ms() -> u64 {
+    CONFIG_LOAD_TIME_MS.load(Ordering::SeqCst)
+}
 
-    // 获取当前快捷键并注销
-    let mut current = CURRENT_SHORTCUT.lock().map_err(|e| e.to_string())?;
-    if let Some((old_mods, old_key)) = current.as_ref() {
-        if let Some(old_code) = parse_key(old_key) {
-            let old_shortcut = Shortcut::new(parse_modifiers(old_mods), old_code);
-            let _ = app.global_shortcut().unregister(old_shortcut);
-        }
+// 重新从磁盘读取配置，用于应用外部对配置文件的修改；尚未写入的修改会先写入
+#[tauri::command]
+fn reload_config_from_disk() -> Result<AppConfig, String> {
+    flush_pending_config();
+    let path = get_config_path().ok_or("配置路径未初始化")?;
+    Ok(CONFIG_CACHE.reload(&path))
+}
+
+// 将旧版本的配置升级到当前版本，缺失字段由 #[serde(default)] 补齐
+// 字段调整需要转换时，在这里按 version 依次升级
+fn migrate_config(mut value: serde_json::Value) -> serde_json::Value {
+    if let Some(obj) = value.as_object_mut() {
+        obj.insert("version".to_string(), serde_json::json!(CONFIG_VERSION));
     }
+    value
+}
 
-    // 注册新快捷键
-    app.global_shortcut()
-        .register(new_shortcut)
-        .map_err(|e| format!("注册快捷键失败: {}", e))?;
+// 配置文件无法解析时改名为 config.json.bak，避免下次保存时覆盖可恢复的内容
+fn backup_corrupt_config(path: &Path, error: &serde_json::Error) {
+    let backup = path.with_extension("json.bak");
+    match fs::rename(path, &backup) {
+        Ok(()) => log::warn!("配置文件解析失败（{}），已备份到 {}", error, backup.display()),
+        Err(e) => log::warn!("配置文件解析失败（{}），备份失败: {}", error, e),
+    }
+}
 
-    // 保存新快捷键配置到内存
-    *current = Some((modifiers.clone(), key.clone()));
+// 保存配置
+fn save_config(config: &AppConfig) -> Result<(), String> {
+    set_cached_config(config);
+    let path = get_config_path().ok_or("配置路径未初始化")?;
+    save_config_to(&PENDING_CONFIG, &path, config)
+}
 
-    // 持久化到文件
-    let mut config = load_config();
-    config.shortcut_modifiers = modifiers.clone();
-    config.shortcut_key = key.clone();
-    save_config(&config);
+// 直接写入的配置已包含待写内容（来自 load_config），丢弃待写配置避免被旧值覆盖；
+// 丢弃和写入都在写入锁内完成，后台线程不会在此之后再写入旧的待写配置
+fn save_config_to(pending: &Mutex<Option<AppConfig>>, path: &Path, config: &AppConfig) -> Result<(), String> {
+    let _guard = CONFIG_WRITE_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Ok(mut pending) = pending.lock() {
+        *pending = None;
+    }
+    write_config_file(path, config)
+}
 
-    // 返回显示用的快捷键字符串
-    let display = format_shortcut_display(&modifiers, &key);
-    Ok(display)
+// 先写入同目录下的临时文件再改名覆盖，避免写入中途退出导致配置文件不完整
+// 调用方需持有 CONFIG_WRITE_LOCK
+fn write_config_file(path: &Path, config: &AppConfig) -> Result<(), String> {
+    if let Ok(mut last) = LAST_CONFIG_WRITE.lock() {
+        *last = Some(Instant::now());
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("创建配置目录失败: {}", e))?;
+    }
+    let content = serde_json::to_string_pretty(config).map_err(|e| e.to_string())?;
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(&tmp_path, content).map_err(|e| format!("写入配置文件失败: {}", e))?;
+    fs::rename(&tmp_path, path).map_err(|e| {
+        let _ = fs::remove_file(&tmp_path);
+        format!("保存配置文件失败: {}", e)
+    })
 }
 
-#[tauri::command]
-fn get_shortcut() -> (Vec<String>, String) {
-    let current = CURRENT_SHORTCUT.lock().unwrap();
-    current.clone().unwrap_or_else(|| (vec!["Alt".to_string()], "M".to_string()))
+// 距上次发生超过指定间隔（或从未发生）时返回 true，用于写入间隔和拖动结束判断
+fn interval_elapsed(last: Option<Instant>, now: Instant, interval: Duration) -> bool {
+    match last {
+        Some(last) => now.saturating_duration_since(last) >= interval,
+        None => true,
+    }
 }
 
-#[tauri::command]
-fn save_window_size(width: f64, height: f64) {
-    let mut config = load_config();
-    config.window_width = width;
-    config.window_height = height;
-    save_config(&config);
+// 将配置放入待写队列，由后台线程合并写入（用于窗口缩放等高频场景）
+fn queue_config_write(config: AppConfig) {
+    set_cached_config(&config);
+    if let Ok(mut pending) = PENDING_CONFIG.lock() {
+        *pending = Some(config);
+    }
 }
 
-fn format_shortcut_display(modifiers: &[String], key: &str) -> String {
-    let mut parts = Vec::new();
-    for m in modifiers {
-        match m.to_uppercase().as_str() {
-            "ALT" | "OPTION" => parts.push("⌥"),
-            "CTRL" | "CONTROL" => parts.push("⌃"),
-            "SHIFT" => parts.push("⇧"),
-            "META" | "COMMAND" | "CMD" | "SUPER" => parts.push("⌘"),
-            _ => {}
+// 立即写入待写配置
+fn flush_pending_config() {
+    if let Some(path) = get_config_path() {
+        flush_pending(&PENDING_CONFIG, &path);
+    }
+}
+
+// 取出待写配置并写入 path，没有待写配置时不写入
+// 在写入锁内取出，避免取出后被 save_config 写入的新配置又被旧的待写配置覆盖
+fn flush_pending(pending: &Mutex<Option<AppConfig>>, path: &Path) {
+    let _guard = CONFIG_WRITE_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+    let config = pending.lock().ok().and_then(|mut p| p.take());
+    if let Some(config) = config {
+        if let Err(e) = write_config_file(path, &config) {
+            log::warn!("{}", e);
         }
     }
-    parts.push(key);
-    parts.join("")
 }
 
-fn show_window(app: &tauri::AppHandle) {
-    if let Some(window) = app.get_webview_window(WINDOW_LABEL) {
-        // 获取主显示器并定位到右上角
-        if let Some(monitor) = window.primary_monitor().ok().flatten() {
-            let screen_size = monitor.size();
-            let scale_factor = monitor.scale_factor();
+// 距上次写入超过最小间隔，且拖动、缩放已结束时才写入，拖动过程中的修改等结束后再合并写入
+fn should_flush(last_write: Option<Instant>, last_move: Option<Instant>, now: Instant, interval: Duration) -> bool {
+    interval_elapsed(last_write, now, interval) && interval_elapsed(last_move, now, MOVE_SETTLE)
+}
 
-            // 获取当前窗口大小
-            let window_size = window.outer_size().unwrap_or(tauri::PhysicalSize::new(
-                (WINDOW_WIDTH * scale_factor) as u32,
-                (WINDOW_HEIGHT * scale_factor) as u32,
-            ));
+// 后台写入线程：按 min_write_interval_ms 合并写入待写配置
+fn start_config_flusher() {
+    std::thread::spawn(|| loop {
+        std::thread::sleep(CONFIG_FLUSH_TICK);
+        let interval = PENDING_CONFIG
+            .lock()
+            .ok()
+            .and_then(|p| p.as_ref().map(|c| Duration::from_millis(c.min_write_interval_ms)));
+        let Some(interval) = interval else {
+            continue;
+        };
+        let now = Instant::now();
+        let last_write = LAST_CONFIG_WRITE.lock().ok().and_then(|last| *last);
+        let last_move = LAST_USER_MOVE.lock().ok().and_then(|last| *last);
+        if should_flush(last_write, last_move, now, interval) {
+            flush_pending_config();
+        }
+    });
+}
 
-            let margin = (10.0 * scale_factor) as i32;
-            let top_margin = (30.0 * scale_factor) as i32;
+// 当前保存的完整配置
+#[tauri::command]
+fn get_config() -> AppConfig {
+    load_config()
+}
 
-            let x = screen_size.width as i32 - window_size.width as i32 - margin;
-            let y = top_margin;
-            let _ = window.set_position(PhysicalPosition::new(x, y));
-        }
-        let _ = window.show();
-        let _ = window.set_focus();
+// 将当前配置导出到指定文件，便于在其他设备上导入
+#[tauri::command]
+fn export_config(path: String) -> Result<(), String> {
+    let path = PathBuf::from(path);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("创建导出目录失败: {}", e))?;
     }
+    let content = serde_json::to_string_pretty(&load_config()).map_err(|e| e.to_string())?;
+    fs::write(&path, content).map_err(|e| format!("导出配置失败: {}", e))
 }
 
-fn toggle_window(app: &tauri::AppHandle) {
+// 从文件导入配置：快捷键无效时返回错误且不修改当前配置，其余可修复的字段自动修复
+#[tauri::command]
+fn import_config(app: AppHandle, path: String) -> Result<AppConfig, String> {
+    let content = fs::read_to_string(&path).map_err(|e| format!("读取配置文件失败: {}", e))?;
+    let mut imported = serde_json::from_str::<serde_json::Value>(&content)
+        .and_then(|value| serde_json::from_value::<AppConfig>(migrate_config(value)))
+        .map_err(|e| format!("配置文件格式错误: {}", e))?;
+
+    // 先校验主快捷键
+    let code =
+        parse_key(&imported.shortcut_key).ok_or_else(|| format!("无效的按键: {}", imported.shortcut_key))?;
+    if has_unknown_modifier(&imported.shortcut_modifiers) {
+        return Err(format!("无效的修饰键: {:?}", imported.shortcut_modifiers));
+    }
+    check_modifier_required(&imported.shortcut_modifiers, code)?;
+    clamp_numeric_fields(&mut imported);
+    let _ = repair_config_fields(&mut imported);
+    check_imported_shortcuts(&imported)?;
+
+    // 没有托盘时开启鼠标穿透将无法再关闭
+    if imported.click_through && !tray_available() {
+        imported.click_through = false;
+    }
+    check_tray_dependency(tray_available(), parse_interaction_model(&imported.interaction_model), false)?;
+
+    replace_user_shortcuts(&app, &load_config(), &imported)?;
+
+    // 应用置顶、不透明度、鼠标穿透、开机自启和 Esc 隐藏等运行时状态
+    PINNED.store(imported.pinned, Ordering::SeqCst);
+    schedule_auto_unpin(auto_unpin_delay(imported.pinned, imported.max_pin_duration_secs));
+    let _ = app.emit("pinned", imported.pinned);
+    if let Err(e) = apply_autostart(&app, imported.autostart) {
+        log::warn!("{}", e);
+    }
+
     if let Some(window) = app.get_webview_window(WINDOW_LABEL) {
-        if window.is_visible().unwrap_or(false) {
-            let _ = window.hide();
-        } else {
-            show_window(app);
+        if !transient_layout_active() {
+            resize_keep_anchor(&window, imported.window_width, imported.window_height);
         }
+        let _ = window.eval(opacity_script(current_opacity(&imported)));
+        match window.set_ignore_cursor_events(imported.click_through) {
+            Ok(()) => CLICK_THROUGH.store(imported.click_through, Ordering::SeqCst),
+            Err(e) => log::warn!("设置鼠标穿透失败: {}", e),
+        }
+        sync_escape_shortcut(&app, window.is_focused().unwrap_or(false));
     }
+    let _ = rebuild_tray_menu(app);
+    Ok(imported)
 }
 
-fn create_window(app: &tauri::AppHandle, config: &AppConfig) -> tauri::Result<()> {
-    let window = WebviewWindowBuilder::new(app, WINDOW_LABEL, WebviewUrl::default())
-        .title("Millionaire")
-        .inner_size(config.window_width, config.window_height)
-        .min_inner_size(WINDOW_WIDTH, WINDOW_HEIGHT)
-        .resizable(true)
-        .decorations(false)
-        .transparent(true)
-        .always_on_top(true)
-        .visible(false)
-        .skip_taskbar(true)
-        .build()?;
-
-    let window_clone = window.clone();
-    window.on_window_event(move |event| {
-        match event {
-            tauri::WindowEvent::Focused(focused) => {
-                // 置顶模式下不自动隐藏
-                if !focused && !PINNED.load(Ordering::SeqCst) {
-                    let _ = window_clone.hide();
-                }
-            }
-            tauri::WindowEvent::Resized(size) => {
-                // 保存窗口大小
-                let scale = window_clone.scale_factor().unwrap_or(1.0);
-                let width = size.width as f64 / scale;
-                let height = size.height as f64 / scale;
-                let mut config = load_config();
-                config.window_width = width;
-                config.window_height = height;
-                save_config(&config);
-            }
-            _ => {}
+// 校验导入配置中的全部快捷键：修饰键数量按导入配置的上限计算，不能使用紧急重置组合，彼此不能重复
+fn check_imported_shortcuts(config: &AppConfig) -> Result<(), String> {
+    let bindings = [
+        (&config.shortcut_modifiers, &config.shortcut_key),
+        (&config.settings_shortcut_modifiers, &config.settings_shortcut_key),
+        (&config.peek_shortcut_modifiers, &config.peek_shortcut_key),
+        (&config.pin_shortcut_modifiers, &config.pin_shortcut_key),
+        (&config.refresh_shortcut_modifiers, &config.refresh_shortcut_key),
+    ];
+    let mut seen = Vec::new();
+    for (modifiers, key) in bindings {
+        let Some(shortcut) = parse_optional_shortcut(modifiers, key)? else {
+            continue;
+        };
+        check_modifier_limit(modifiers, config.max_modifiers)?;
+        if shortcut == emergency_shortcut() {
+            return Err("该组合为保留的紧急重置快捷键".to_string());
         }
-    });
+        if seen.contains(&shortcut) {
+            return Err("快捷键冲突：该组合已被其他功能使用".to_string());
+        }
+        seen.push(shortcut);
+    }
+    Ok(())
+}
 
+// 先注销全部用户快捷键，再按新配置的交互模式注册，避免新旧绑定互相冲突（如两个动作互换组合）；
+// 注册或保存失败时恢复原快捷键和原配置
+fn replace_user_shortcuts(app: &AppHandle, previous: &AppConfig, updated: &AppConfig) -> Result<(), String> {
+    let mut current = lock_current_shortcut();
+    unregister_user_shortcuts(app);
+    let model = parse_interaction_model(&updated.interaction_model);
+    if let Err(e) = apply_interaction_model(app, updated, model) {
+        restore_user_shortcuts(app, previous);
+        return Err(e);
+    }
+    if let Err(e) = save_config(updated) {
+        set_cached_config(previous);
+        restore_user_shortcuts(app, previous);
+        return Err(e);
+    }
+    *current = Some((updated.shortcut_modifiers.clone(), updated.shortcut_key.clone()));
     Ok(())
 }
 
-#[cfg_attr(mobile, tauri::mobile_entry_point)]
-pub fn run() {
-    tauri::Builder::default()
-        .plugin(tauri_plugin_http::init())
-        .plugin(tauri_plugin_store::Builder::default().build())
-        .invoke_handler(tauri::generate_handler![set_pinned, get_pinned, update_shortcut, get_shortcut, save_window_size])
-        .plugin(
-            tauri_plugin_global_shortcut::Builder::new()
-                .with_handler(|app, _shortcut, event| {
-                    // 任何已注册的快捷键触发时都切换窗口
-                    if event.state() == ShortcutState::Pressed {
-                        toggle_window(app);
-                    }
-                })
-                .build(),
-        )
-        .setup(|app| {
-            // 初始化配置文件路径
-            if let Some(app_data_dir) = app.path().app_data_dir().ok() {
-                let config_path = app_data_dir.join(CONFIG_FILE);
-                if let Ok(mut path) = CONFIG_PATH.lock() {
-                    *path = Some(config_path);
-                }
-            }
+// 按原配置重新注册用户快捷键，用于修改失败后的恢复
+fn restore_user_shortcuts(app: &tauri::AppHandle, previous: &AppConfig) {
+    unregister_user_shortcuts(app);
+    let model = parse_interaction_model(&previous.interaction_model);
+    if let Err(e) = apply_interaction_model(app, previous, model) {
+        log::warn!("恢复快捷键失败: {}", e);
+    }
+}
 
-            // 加载配置
-            let config = load_config();
+// 配置默认值，供前端逐项恢复默认
+#[tauri::command]
+fn config_defaults() -> AppConfig {
+    AppConfig::default()
+}
 
-            // 创建窗口
-            create_window(app.handle(), &config)?;
-
-            // 创建托盘菜单 - 左键点击直接显示菜单
-            let shortcut_display = format_shortcut_display(&config.shortcut_modifiers, &config.shortcut_key);
-            let show_item = MenuItem::with_id(app, "show", format!("显示面板 ({})", shortcut_display), true, None::<&str>)?;
-            let quit_item = MenuItem::with_id(app, "quit", "退出", true, None::<&str>)?;
-            let menu = Menu::with_items(app, &[&show_item, &quit_item])?;
-
-            // 获取配置中的 tray icon
-            let tray = app.tray_by_id("main").expect("tray not found");
-            tray.set_menu(Some(menu))?;
-            tray.set_show_menu_on_left_click(true)?;
-            tray.on_menu_event(|app, event| {
-                match event.id.as_ref() {
-                    "show" => show_window(app),
-                    "quit" => app.exit(0),
-                    _ => {}
-                }
-            });
+#[tauri::command]
+fn config_size_bytes() -> usize {
+    serialized_size(&load_config())
+}
 
-            // 注册快捷键（从配置加载）
-            if let Some(code) = parse_key(&config.shortcut_key) {
-                let mods = parse_modifiers(&config.shortcut_modifiers);
-                let shortcut = Shortcut::new(mods, code);
-                app.global_shortcut().register(shortcut)?;
-            }
+// 配置序列化后的字节数（与写入文件的格式一致）
+fn serialized_size(config: &AppConfig) -> usize {
+    serde_json::to_string_pretty(config)
+        .map(|content| content.len())
+        .unwrap_or(0)
+}
 
-            // 初始化快捷键配置到内存
-            let mut current = CURRENT_SHORTCUT.lock().unwrap();
-            *current = Some((config.shortcut_modifiers.clone(), config.shortcut_key.clone()));
+// 将窗口尺寸限制在合理范围内，非法数值回退到默认值
+fn clamp_dimension(value: f64, min: f64) -> f64 {
+    if value.is_finite() {
+        value.clamp(min, MAX_WINDOW_SIZE)
+    } else {
+        min
+    }
+}
 
-            Ok(())
-        })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+// 将配置中的尺寸等数值限制在合理范围内，避免手动编辑或损坏的数值导致面板无法使用
+fn clamp_numeric_fields(config: &mut AppConfig) {
+    config.window_width = clamp_dimension(config.window_width, WINDOW_WIDTH);
+    config.window_height = clamp_dimension(config.window_height, WINDOW_HEIGHT);
+    config.hover_expand_width = clamp_dimension(config.hover_expand_width, WINDOW_WIDTH);
+    config.hover_expand_height = clamp_dimension(config.hover_expand_height, WINDOW_HEIGHT);
+    config.small_screen_width = clamp_dimension(config.small_screen_width, DOT_SIZE);
+    config.small_screen_height = clamp_dimension(config.small_screen_height, DOT_SIZE);
+    config.resize_border_px = clamp_resize_border(config.resize_border_px);
+    config.opacity = clamp_opacity(config.opacity);
+    config.night_opacity = config.night_opacity.map(clamp_opacity);
+}
+
+// 修复配置中可修复的问题，返回修改说明；合法字段保持不变
+fn repair_config_fields(config: &mut AppConfig) -> Vec<String> {
+    let defaults = AppConfig::default();
+    let mut changes = Vec::new();
+
+    let width = clamp_dimension(config.window_width, WINDOW_WIDTH);
+    if width != config.window_width {
+        changes.push(format!("window_width: {} -> {}", config.window_width, width));
+        config.window_width = width;
+    }
+    let height = clamp_dimension(config.window_height, WINDOW_HEIGHT);
+    if height != config.window_height {
+        changes.push(format!("window_height: {} -> {}", config.window_height, height));
+        config.window_height = height;
+    }
+
+    if parse_key(&config.shortcut_key).is_none() {
+        changes.push(format!("shortcut_key: {} -> {}", config.shortcut_key, defaults.shortcut_key));
+        config.shortcut_key = defaults.shortcut_key.clone();
+        config.shortcut_modifiers = defaults.shortcut_modifiers.clone();
+    }
+    repair_modifiers("shortcut_modifiers", &mut config.shortcut_modifiers, &mut changes);
+    repair_optional_key("settings_shortcut_key", &mut config.settings_shortcut_key, &mut changes);
+    repair_modifiers("settings_shortcut_modifiers", &mut config.settings_shortcut_modifiers, &mut changes);
+    repair_optional_key("peek_shortcut_key", &mut config.peek_shortcut_key, &mut changes);
+    repair_modifiers("peek_shortcut_modifiers", &mut config.peek_shortcut_modifiers, &mut changes);
+    repair_optional_key("pin_shortcut_key", &mut config.pin_shortcut_key, &mut changes);
+    repair_modifiers("pin_shortcut_modifiers", &mut config.pin_shortcut_modifiers, &mut changes);
+    repair_optional_key("refresh_shortcut_key", &mut config.refresh_shortcut_key, &mut changes);
+    repair_modifiers("refresh_shortcut_modifiers", &mut config.refresh_shortcut_modifiers, &mut changes);
+
+    if config.time_format != "24h" && config.time_format != "12h" {
+        changes.push(format!("time_format: {} -> {}", config.time_format, defaults.time_format));
+        config.time_format = defaults.time_format;
+    }
+
+    if parse_anchor(&config.anchor).is_none() {
+        changes.push(format!("anchor: {} -> {}", config.anchor, defaults.anchor));
+        config.anchor = defaults.anchor.clone();
+    }
+
+    if !is_valid_drag_region(&config.drag_region) {
+        changes.push(format!("drag_region: {} -> {}", config.drag_region, defaults.drag_region));
+        config.drag_region = defaults.drag_region;
+    }
+
+    let border = clamp_resize_border(config.resize_border_px);
+    if border != config.resize_border_px {
+        changes.push(format!("resize_border_px: {} -> {}", config.resize_border_px, border));
+        config.resize_border_px = border;
+    }
+
+    if parse_clock(&config.night_start).is_none() {
+        changes.push(format!("night_start: {} -> {}", config.night_start, defaults.night_start));
+        config.night_start = defaults.night_start.clone();
+    }
+    if parse_clock(&config.night_end).is_none() {
+        changes.push(format!("night_end: {} -> {}", config.night_end, defaults.night_end));
+        config.night_end = defaults.night_end.clone();
+    }
+
+    let mode = layout_mode_name(parse_layout_mode(&config.last_layout_mode));
+    if mode != config.last_layout_mode {
+        changes.push(format!("last_layout_mode: {} -> {}", config.last_layout_mode, mode));
+        config.last_layout_mode = mode.to_string();
+    }
+
+    let opacity = clamp_opacity(config.opacity);
+    if opacity != config.opacity {
+        changes.push(format!("opacity: {} -> {}", config.opacity, opacity));
+        config.opacity = opacity;
+    }
+
+    changes
+}
+
+// 存在无法识别的修饰键时去掉它们；合法的组合保持原有名称和顺序，如 ["Cmd", "Option"]
+fn repair_modifiers(field: &str, modifiers: &mut Vec<String>, changes: &mut Vec<String>) {
+    if has_unknown_modifier(modifiers) {
+        let normalized = normalize_modifiers(modifiers);
+        changes.push(format!("{}: {:?} -> {:?}", field, modifiers, normalized));
+        *modifiers = normalized;
+    }
+}
+
+// 可选快捷键的按键无法识别时视为未启用
+fn repair_optional_key(field: &str, key: &mut String, changes: &mut Vec<String>) {
+    if !key.is_empty() && parse_key(key).is_none() {
+        changes.push(format!("{}: {} -> (未启用)", field, key));
+        key.clear();
+    }
+}
+
+#[tauri::command]
+fn repair_config() -> Result<Vec<String>, String> {
+    let mut config = load_config();
+    let changes = repair_config_fields(&mut config);
+    if !changes.is_empty() {
+        save_config(&config)?;
+    }
+    Ok(changes)
+}
+
+// 保存 API Key 的键值存储，应用中为 store 插件，测试中可替换为内存实现
+trait SecretStore {
+    fn get(&self, key: &str) -> Option<serde_json::Value>;
+    fn set(&self, key: &str, value: serde_json::Value);
+    fn delete(&self, key: &str);
+    fn save(&self) -> Result<(), String>;
+}
+
+impl<R: tauri::Runtime> SecretStore for tauri_plugin_store::Store<R> {
+    fn get(&self, key: &str) -> Option<serde_json::Value> {
+        tauri_plugin_store::Store::get(self, key)
+    }
+
+    fn set(&self, key: &str, value: serde_json::Value) {
+        tauri_plugin_store::Store::set(self, key, value);
+    }
+
+    fn delete(&self, key: &str) {
+        tauri_plugin_store::Store::delete(self, key);
+    }
+
+    fn save(&self) -> Result<(), String> {
+        tauri_plugin_store::Store::save(self).map_err(|e| e.to_string())
+    }
+}
+
+fn store_api_key(store: &impl SecretStore, key: String) -> Result<(), String> {
+    store.set(API_KEY_ENTRY, key.into());
+    store.save().map_err(|e| format!("保存 API Key 失败: {}", e))
+}
+
+fn read_api_key(store: &impl SecretStore) -> Option<String> {
+    store.get(API_KEY_ENTRY)?.as_str().map(str::to_string)
+}
+
+fn remove_api_key(store: &impl SecretStore) -> Result<(), String> {
+    store.delete(API_KEY_ENTRY);
+    store.save().map_err(|e| format!("保存 API Key 失败: {}", e))
+}
+
+#[tauri::command]
+fn set_api_key(app: AppHandle, key: String) -> Result<(), String> {
+    let store = app.store(SECRETS_STORE).map_err(|e| e.to_string())?;
+    store_api_key(&*store, key)
+}
+
+#[tauri::command]
+fn get_api_key(app: AppHandle) -> Option<String> {
+    read_api_key(&*app.store(SECRETS_STORE).ok()?)
+}
+
+#[tauri::command]
+fn clear_api_key(app: AppHandle) -> Result<(), String> {
+    let store = app.store(SECRETS_STORE).map_err(|e| e.to_string())?;
+    remove_api_key(&*store)
+}
+
+#[tauri::command]
+fn set_pinned(pinned: bool) -> Result<(), String> {
+    PINNED.store(pinned, Ordering::SeqCst);
+
+    // 持久化到文件
+    let mut config = load_config();
+    config.pinned = pinned;
+    let saved = save_config(&config);
+
+    // 到达最长置顶时间后自动取消置顶，重新置顶时重新计时，取消置顶时停止计时
+    schedule_auto_unpin(auto_unpin_delay(pinned, config.max_pin_duration_secs));
+    saved
+}
+
+// 可重置的定时器：收到时长后开始计时，到期调用 on_fire；计时期间收到新的时长重新计时，收到 None 取消
+// 发送端全部丢弃后线程退出
+fn spawn_reset_timer(mut on_fire: impl FnMut() + Send + 'static) -> Sender<Option<Duration>> {
+    let (sender, receiver) = mpsc::channel::<Option<Duration>>();
+    std::thread::spawn(move || {
+        let mut deadline: Option<Instant> = None;
+        loop {
+            let received = match deadline {
+                Some(deadline) => receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())),
+                None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            match received {
+                Ok(delay) => deadline = delay.map(|delay| Instant::now() + delay),
+                Err(RecvTimeoutError::Timeout) => {
+                    deadline = None;
+                    on_fire();
+                }
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+    });
+    sender
+}
+
+// 自动取消置顶的计时时长，未置顶或不限制置顶时间时为 None
+fn auto_unpin_delay(pinned: bool, max_pin_duration_secs: u64) -> Option<Duration> {
+    if pinned && max_pin_duration_secs > 0 {
+        Some(Duration::from_secs(max_pin_duration_secs))
+    } else {
+        None
+    }
+}
+
+// 启动自动取消置顶的定时器
+fn start_auto_unpin_timer(app: AppHandle) {
+    let timer = spawn_reset_timer(move || {
+        if PINNED.swap(false, Ordering::SeqCst) {
+            let mut config = load_config();
+            config.pinned = false;
+            if let Err(e) = save_config(&config) {
+                log::warn!("{}", e);
+            }
+            let _ = app.emit("auto-unpinned", ());
+            // 同步前端的置顶按钮状态
+            let _ = app.emit("pinned", false);
+        }
+    });
+    if let Ok(mut slot) = AUTO_UNPIN_TIMER.lock() {
+        *slot = Some(timer);
+    }
+}
+
+// 重新开始或取消自动取消置顶的计时
+fn schedule_auto_unpin(delay: Option<Duration>) {
+    if let Some(timer) = AUTO_UNPIN_TIMER.lock().ok().and_then(|timer| timer.clone()) {
+        let _ = timer.send(delay);
+    }
+}
+
+#[tauri::command]
+fn set_max_pin_duration(secs: u64) -> Result<(), String> {
+    let mut config = load_config();
+    config.max_pin_duration_secs = secs;
+    save_config(&config)
+}
+
+#[tauri::command]
+fn get_pinned() -> bool {
+    PINNED.load(Ordering::SeqCst)
+}
+
+// 判断启动参数中是否带有开机自启标记
+fn is_autostart_launch(args: &[String]) -> bool {
+    args.iter().skip(1).any(|arg| arg == AUTOSTART_ARG)
+}
+
+#[tauri::command]
+fn launched_at_login() -> bool {
+    LAUNCHED_AT_LOGIN.load(Ordering::SeqCst)
+}
+
+// 在系统中开启或关闭开机自启
+fn apply_autostart(app: &AppHandle, enabled: bool) -> Result<(), String> {
+    let autolaunch = app.autolaunch();
+    let result = if enabled { autolaunch.enable() } else { autolaunch.disable() };
+    result.map_err(|e| format!("设置开机自启失败: {}", e))
+}
+
+#[tauri::command]
+fn set_autostart(app: AppHandle, enabled: bool) -> Result<(), String> {
+    apply_autostart(&app, enabled)?;
+    let mut config = load_config();
+    config.autostart = enabled;
+    save_config(&config)?;
+    Ok(())
+}
+
+// 以系统中的实际自启状态为准同步配置，避免用户在系统设置中修改后不一致
+fn reconcile_autostart(app: &AppHandle) {
+    match app.autolaunch().is_enabled() {
+        Ok(enabled) => {
+            let mut config = load_config();
+            if config.autostart != enabled {
+                config.autostart = enabled;
+                if let Err(e) = save_config(&config) {
+                    log::warn!("{}", e);
+                }
+            }
+        }
+        Err(e) => log::warn!("读取开机自启状态失败: {}", e),
+    }
+}
+
+// 解析修饰键
+fn parse_modifiers(mods: &[String]) -> Option<Modifiers> {
+    if mods.is_empty() {
+        return None;
+    }
+    let mut result = Modifiers::empty();
+    for m in mods {
+        match m.to_uppercase().as_str() {
+            "ALT" | "OPTION" => result |= Modifiers::ALT,
+            "CTRL" | "CONTROL" => result |= Modifiers::CONTROL,
+            "SHIFT" => result |= Modifiers::SHIFT,
+            "META" | "COMMAND" | "CMD" | "SUPER" => result |= Modifiers::META,
+            _ => {}
+        }
+    }
+    Some(result)
+}
+
+// 各平台推荐的修饰键组合
+#[cfg(target_os = "macos")]
+const MODIFIER_PRESETS: &[&[&str]] = &[&["Option"], &["Cmd", "Option"], &["Cmd", "Shift"], &["Ctrl", "Option"]];
+
+#[cfg(not(target_os = "macos"))]
+const MODIFIER_PRESETS: &[&[&str]] = &[&["Alt"], &["Ctrl", "Alt"], &["Ctrl", "Shift"], &["Alt", "Shift"]];
+
+#[tauri::command]
+fn allowed_modifier_presets() -> Vec<Vec<String>> {
+    MODIFIER_PRESETS
+        .iter()
+        .map(|preset| preset.iter().map(|m| m.to_string()).collect())
+        .collect()
+}
+
+// 是否包含无法识别的修饰键名称
+fn has_unknown_modifier(mods: &[String]) -> bool {
+    mods.iter()
+        .any(|m| parse_modifiers(std::slice::from_ref(m)).map_or(true, |flags| flags.is_empty()))
+}
+
+// 规范化修饰键：统一名称、去重并按固定顺序排列，丢弃无法识别的修饰键
+fn normalize_modifiers(mods: &[String]) -> Vec<String> {
+    let mut flags = Modifiers::empty();
+    for m in mods {
+        if let Some(parsed) = parse_modifiers(std::slice::from_ref(m)) {
+            flags |= parsed;
+        }
+    }
+    [
+        (Modifiers::ALT, "Alt"),
+        (Modifiers::CONTROL, "Ctrl"),
+        (Modifiers::SHIFT, "Shift"),
+        (Modifiers::META, "Meta"),
+    ]
+    .iter()
+    .filter(|(flag, _)| flags.contains(*flag))
+    .map(|(_, name)| name.to_string())
+    .collect()
+}
+
+// 两组快捷键是否等价：修饰键规范化后相同且按键解析为同一个 Code
+fn same_shortcut(a_mods: &[String], a_key: &str, b_mods: &[String], b_key: &str) -> bool {
+    let a_code = parse_key(a_key);
+    a_code.is_some() && a_code == parse_key(b_key) && normalize_modifiers(a_mods) == normalize_modifiers(b_mods)
+}
+
+// 支持的按键：规范名称与对应的 Code，parse_key 与 supported_keys 共用此表
+const KEYS: &[(&str, Code)] = &[
+    ("A", Code::KeyA),
+    ("B", Code::KeyB),
+    ("C", Code::KeyC),
+    ("D", Code::KeyD),
+    ("E", Code::KeyE),
+    ("F", Code::KeyF),
+    ("G", Code::KeyG),
+    ("H", Code::KeyH),
+    ("I", Code::KeyI),
+    ("J", Code::KeyJ),
+    ("K", Code::KeyK),
+    ("L", Code::KeyL),
+    ("M", Code::KeyM),
+    ("N", Code::KeyN),
+    ("O", Code::KeyO),
+    ("P", Code::KeyP),
+    ("Q", Code::KeyQ),
+    ("R", Code::KeyR),
+    ("S", Code::KeyS),
+    ("T", Code::KeyT),
+    ("U", Code::KeyU),
+    ("V", Code::KeyV),
+    ("W", Code::KeyW),
+    ("X", Code::KeyX),
+    ("Y", Code::KeyY),
+    ("Z", Code::KeyZ),
+    ("0", Code::Digit0),
+    ("1", Code::Digit1),
+    ("2", Code::Digit2),
+    ("3", Code::Digit3),
+    ("4", Code::Digit4),
+    ("5", Code::Digit5),
+    ("6", Code::Digit6),
+    ("7", Code::Digit7),
+    ("8", Code::Digit8),
+    ("9", Code::Digit9),
+    ("F1", Code::F1),
+    ("F2", Code::F2),
+    ("F3", Code::F3),
+    ("F4", Code::F4),
+    ("F5", Code::F5),
+    ("F6", Code::F6),
+    ("F7", Code::F7),
+    ("F8", Code::F8),
+    ("F9", Code::F9),
+    ("F10", Code::F10),
+    ("F11", Code::F11),
+    ("F12", Code::F12),
+    ("Space", Code::Space),
+    ("Enter", Code::Enter),
+    ("Escape", Code::Escape),
+    ("Tab", Code::Tab),
+    ("ArrowUp", Code::ArrowUp),
+    ("ArrowDown", Code::ArrowDown),
+    ("ArrowLeft", Code::ArrowLeft),
+    ("ArrowRight", Code::ArrowRight),
+    ("Backspace", Code::Backspace),
+    ("Delete", Code::Delete),
+    ("Home", Code::Home),
+    ("End", Code::End),
+    ("PageUp", Code::PageUp),
+    ("PageDown", Code::PageDown),
+    ("Numpad0", Code::Numpad0),
+    ("Numpad1", Code::Numpad1),
+    ("Numpad2", Code::Numpad2),
+    ("Numpad3", Code::Numpad3),
+    ("Numpad4", Code::Numpad4),
+    ("Numpad5", Code::Numpad5),
+    ("Numpad6", Code::Numpad6),
+    ("Numpad7", Code::Numpad7),
+    ("Numpad8", Code::Numpad8),
+    ("Numpad9", Code::Numpad9),
+    ("NumpadAdd", Code::NumpadAdd),
+    ("NumpadSubtract", Code::NumpadSubtract),
+    ("NumpadMultiply", Code::NumpadMultiply),
+    ("NumpadDivide", Code::NumpadDivide),
+    ("NumpadEnter", Code::NumpadEnter),
+];
+
+// 按键别名（大写）及对应的规范名称
+const KEY_ALIASES: &[(&str, &str)] = &[
+    ("DIGIT0", "0"),
+    ("DIGIT1", "1"),
+    ("DIGIT2", "2"),
+    ("DIGIT3", "3"),
+    ("DIGIT4", "4"),
+    ("DIGIT5", "5"),
+    ("DIGIT6", "6"),
+    ("DIGIT7", "7"),
+    ("DIGIT8", "8"),
+    ("DIGIT9", "9"),
+    ("ESC", "Escape"),
+    ("UP", "ArrowUp"),
+    ("DOWN", "ArrowDown"),
+    ("LEFT", "ArrowLeft"),
+    ("RIGHT", "ArrowRight"),
+    ("DEL", "Delete"),
+    ("PGUP", "PageUp"),
+    ("PGDN", "PageDown"),
+];
+
+// 解析按键，忽略大小写并支持别名
+fn parse_key(key: &str) -> Option<Code> {
+    let upper = key.to_uppercase();
+    let name = KEY_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == upper)
+        .map(|(_, name)| *name)
+        .unwrap_or(key);
+    KEYS.iter()
+        .find(|(canonical, _)| canonical.eq_ignore_ascii_case(name))
+        .map(|(_, code)| *code)
+}
+
+// 所有支持的按键（规范名称），供设置界面列出可选按键
+#[tauri::command]
+fn supported_keys() -> Vec<String> {
+    KEYS.iter().map(|(name, _)| name.to_string()).collect()
+}
+
+// 检查修饰键数量是否超出上限，同一修饰键的不同写法（如 Ctrl 与 Control）只计一次
+fn check_modifier_limit(modifiers: &[String], max: Option<usize>) -> Result<(), String> {
+    let count = normalize_modifiers(modifiers).len();
+    match max {
+        Some(max) if count > max => Err(format!("修饰键过多: 最多 {} 个，当前 {} 个", max, count)),
+        _ => Ok(()),
+    }
+}
+
+// 按键单独作为全局快捷键时是否必须配合修饰键；功能键与 Esc 可以单独使用
+fn requires_modifier(code: Code) -> bool {
+    !matches!(
+        code,
+        Code::F1
+            | Code::F2
+            | Code::F3
+            | Code::F4
+            | Code::F5
+            | Code::F6
+            | Code::F7
+            | Code::F8
+            | Code::F9
+            | Code::F10
+            | Code::F11
+            | Code::F12
+            | Code::Escape
+    )
+}
+
+// 没有修饰键时拒绝普通按键，避免占用正常输入
+fn check_modifier_required(modifiers: &[String], code: Code) -> Result<(), String> {
+    if normalize_modifiers(modifiers).is_empty() && requires_modifier(code) {
+        return Err("该按键需要配合修饰键使用".to_string());
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn key_allows_no_modifier(key: String) -> bool {
+    parse_key(&key).map(|code| !requires_modifier(code)).unwrap_or(false)
+}
+
+// 注销当前实际注册的快捷键并注册新快捷键，失败时恢复原快捷键，避免没有可用的快捷键
+// 返回替换后实际注册的快捷键和注册结果
+fn swap_shortcut(
+    old: Option<Shortcut>,
+    new: Shortcut,
+    mut unregister: impl FnMut(Shortcut),
+    mut register: impl FnMut(Shortcut) -> Result<(), String>,
+) -> (Option<Shortcut>, Result<(), String>) {
+    if let Some(old) = old {
+        unregister(old);
+    }
+    match register(new) {
+        Ok(()) => (Some(new), Ok(())),
+        Err(e) => {
+            let restored = old.filter(|old| register(*old).is_ok());
+            (restored, Err(e))
+        }
+    }
+}
+
+#[tauri::command]
+fn update_shortcut(app: AppHandle, modifiers: Vec<String>, key: String) -> Result<String, String> {
+    check_modifier_limit(&modifiers, load_config().max_modifiers)?;
+
+    // 解析新快捷键
+    let mods = parse_modifiers(&modifiers);
+    let code = parse_key(&key).ok_or_else(|| format!("无效的按键: {}", key))?;
+    check_modifier_required(&modifiers, code)?;
+    let new_shortcut = Shortcut::new(mods, code);
+    if new_shortcut == emergency_shortcut() {
+        return Err("该组合为保留的紧急重置快捷键".to_string());
+    }
+    check_shortcut_conflict(&new_shortcut, ShortcutAction::Toggle, &load_config())?;
+
+    let mut current = lock_current_shortcut();
+    if global_shortcuts_enabled() {
+        // 与当前快捷键相同且已成功注册时无需重新注册；注册失败或被注销时重新输入同一组合可修复
+        if let Some((old_mods, old_key)) = current.as_ref() {
+            if same_shortcut(old_mods, old_key, &modifiers, &key)
+                && registered_shortcut(ShortcutAction::Toggle) == Some(new_shortcut)
+            {
+                return Ok(format_shortcut_display(&modifiers, &key));
+            }
+        }
+
+        let (registered, result) = swap_shortcut(
+            registered_shortcut(ShortcutAction::Toggle),
+            new_shortcut,
+            |shortcut| {
+                let _ = app.global_shortcut().unregister(shortcut);
+            },
+            |shortcut| app.global_shortcut().register(shortcut).map_err(|e| e.to_string()),
+        );
+        set_registered_shortcut(ShortcutAction::Toggle, registered);
+        result.map_err(|e| format!("注册快捷键失败: {}", e))?;
+    }
+
+    // 保存新快捷键配置到内存
+    *current = Some((modifiers.clone(), key.clone()));
+
+    // 持久化到文件
+    let mut config = load_config();
+    config.shortcut_modifiers = modifiers.clone();
+    config.shortcut_key = key.clone();
+    save_config(&config)?;
+
+    // 同步托盘菜单中的快捷键显示
+    let _ = rebuild_tray_menu(app.clone());
+
+    // 返回显示用的快捷键字符串
+    let display = format_shortcut_display(&modifiers, &key);
+    Ok(display)
+}
+
+// 恢复默认配置，重新注册默认快捷键并还原窗口大小
+#[tauri::command]
+fn reset_config(app: AppHandle) -> Result<(), String> {
+    // 开机自启由系统登录项决定，保留当前值，避免重置后 reconcile_autostart 又按系统状态改回
+    let defaults = AppConfig { autostart: load_config().autostart, ..AppConfig::default() };
+    let default_code =
+        parse_key(&defaults.shortcut_key).ok_or_else(|| format!("无效的按键: {}", defaults.shortcut_key))?;
+    let default_shortcut = Shortcut::new(parse_modifiers(&defaults.shortcut_modifiers), default_code);
+
+    // 先注销全部用户快捷键（默认配置下设置页、速览、置顶快捷键均未启用），
+    // 避免它们占用默认组合导致注册失败；注册失败时按原配置恢复，避免没有可用的快捷键
+    {
+        let mut current = lock_current_shortcut();
+        unregister_user_shortcuts(&app);
+        if let Err(e) = app.global_shortcut().register(default_shortcut) {
+            let previous = load_config();
+            let model = parse_interaction_model(&previous.interaction_model);
+            if let Err(restore) = apply_interaction_model(&app, &previous, model) {
+                log::warn!("恢复快捷键失败: {}", restore);
+            }
+            return Err(format!("注册快捷键失败: {}", e));
+        }
+        set_registered_shortcut(ShortcutAction::Toggle, Some(default_shortcut));
+        *current = Some((defaults.shortcut_modifiers.clone(), defaults.shortcut_key.clone()));
+    }
+
+    PINNED.store(defaults.pinned, Ordering::SeqCst);
+    schedule_auto_unpin(None);
+    set_session_position(None);
+    AUTO_HIDE.store(interaction_effects(parse_interaction_model(&defaults.interaction_model)).auto_hide, Ordering::SeqCst);
+
+    save_config(&defaults)?;
+
+    // 退出圆点模式、小屏幕尺寸、悬停展开和详情气泡等临时布局
+    DOT_MODE.store(false, Ordering::SeqCst);
+    SMALL_SCREEN.store(false, Ordering::SeqCst);
+    HOVER_EXPANDED.store(false, Ordering::SeqCst);
+    if let Ok(mut base) = BUBBLE_BASE_WIDTH.lock() {
+        *base = None;
+    }
+
+    if let Some(window) = app.get_webview_window(WINDOW_LABEL) {
+        let _ = window.set_min_size(Some(LogicalSize::new(WINDOW_WIDTH, WINDOW_HEIGHT)));
+        let _ = window.set_size(LogicalSize::new(WINDOW_WIDTH, WINDOW_HEIGHT));
+        let _ = window.eval(opacity_script(current_opacity(&defaults)));
+        let _ = window.set_ignore_cursor_events(defaults.click_through);
+    }
+    CLICK_THROUGH.store(defaults.click_through, Ordering::SeqCst);
+    let _ = app.emit("pinned", defaults.pinned);
+    let _ = app.emit("dot-mode", false);
+    let _ = rebuild_tray_menu(app);
+    Ok(())
+}
+
+#[tauri::command]
+fn get_shortcut() -> (Vec<String>, String) {
+    lock_current_shortcut().clone().unwrap_or_else(|| {
+        let defaults = AppConfig::default();
+        (defaults.shortcut_modifiers, defaults.shortcut_key)
+    })
+}
+
+#[tauri::command]
+fn update_settings_shortcut(app: AppHandle, modifiers: Vec<String>, key: String) -> Result<String, String> {
+    bind_optional_shortcut(&app, ShortcutAction::Settings, &modifiers, &key)?;
+
+    // 持久化到文件
+    let mut config = load_config();
+    config.settings_shortcut_modifiers = modifiers.clone();
+    config.settings_shortcut_key = key.clone();
+    save_config(&config)?;
+
+    if key.is_empty() {
+        return Ok(String::new());
+    }
+    Ok(format_shortcut_display(&modifiers, &key))
+}
+
+// 快捷键已绑定到本应用的其他动作时返回冲突错误
+// 与配置中的绑定比较而不是已注册的快捷键：仅托盘模式下快捷键都未注册，也要拒绝重复绑定
+fn check_shortcut_conflict(shortcut: &Shortcut, action: ShortcutAction, config: &AppConfig) -> Result<(), String> {
+    match find_action(&configured_bindings(config), shortcut) {
+        Some(other) if other != action => Err("快捷键冲突：该组合已被其他功能使用".to_string()),
+        _ => Ok(()),
+    }
+}
+
+// 配置中各动作绑定的快捷键，未设置或无效的绑定跳过；紧急重置快捷键始终占用
+fn configured_bindings(config: &AppConfig) -> Vec<(Shortcut, ShortcutAction)> {
+    let bindings = [
+        (ShortcutAction::Toggle, &config.shortcut_modifiers, &config.shortcut_key),
+        (ShortcutAction::Settings, &config.settings_shortcut_modifiers, &config.settings_shortcut_key),
+        (ShortcutAction::Peek, &config.peek_shortcut_modifiers, &config.peek_shortcut_key),
+        (ShortcutAction::Pin, &config.pin_shortcut_modifiers, &config.pin_shortcut_key),
+        (ShortcutAction::Refresh, &config.refresh_shortcut_modifiers, &config.refresh_shortcut_key),
+    ];
+    let mut configured: Vec<_> = bindings
+        .into_iter()
+        .filter_map(|(action, modifiers, key)| Some((parse_optional_shortcut(modifiers, key).ok()??, action)))
+        .collect();
+    configured.push((emergency_shortcut(), ShortcutAction::EmergencyReset));
+    configured
+}
+
+// 查找快捷键对应的动作
+fn shortcut_action(shortcut: &Shortcut) -> Option<ShortcutAction> {
+    find_action(&SHORTCUT_REGISTRY.lock().ok()?, shortcut)
+}
+
+fn find_action(registry: &[(Shortcut, ShortcutAction)], shortcut: &Shortcut) -> Option<ShortcutAction> {
+    registry
+        .iter()
+        .find(|(registered, _)| registered == shortcut)
+        .map(|(_, action)| *action)
+}
+
+// 当前绑定到某个动作的快捷键
+fn registered_shortcut(action: ShortcutAction) -> Option<Shortcut> {
+    SHORTCUT_REGISTRY
+        .lock()
+        .ok()?
+        .iter()
+        .find(|(_, registered)| *registered == action)
+        .map(|(shortcut, _)| *shortcut)
+}
+
+// 更新动作绑定的快捷键，None 表示移除
+fn set_registered_shortcut(action: ShortcutAction, shortcut: Option<Shortcut>) {
+    if let Ok(mut registry) = SHORTCUT_REGISTRY.lock() {
+        registry.retain(|(_, registered)| *registered != action);
+        if let Some(shortcut) = shortcut {
+            registry.push((shortcut, action));
+        }
+    }
+}
+
+// 解析可选的快捷键，按键为空时返回 None
+fn parse_optional_shortcut(modifiers: &[String], key: &str) -> Result<Option<Shortcut>, String> {
+    if key.is_empty() {
+        return Ok(None);
+    }
+    let code = parse_key(key).ok_or_else(|| format!("无效的按键: {}", key))?;
+    check_modifier_required(modifiers, code)?;
+    Ok(Some(Shortcut::new(parse_modifiers(modifiers), code)))
+}
+
+// 仅托盘模式下不注册全局快捷键，修改绑定时只保存，切回快捷键模式时再注册
+fn global_shortcuts_enabled() -> bool {
+    interaction_effects(parse_interaction_model(&load_config().interaction_model)).global_shortcuts
+}
+
+// 修改可选快捷键的绑定：快捷键模式下重新注册，仅托盘模式下只做校验
+fn bind_optional_shortcut(
+    app: &AppHandle,
+    action: ShortcutAction,
+    modifiers: &[String],
+    key: &str,
+) -> Result<(), String> {
+    let config = load_config();
+    if global_shortcuts_enabled() {
+        rebind_optional_shortcut(app, action, modifiers, key, &config)
+    } else {
+        check_modifier_limit(modifiers, config.max_modifiers)?;
+        if let Some(shortcut) = parse_optional_shortcut(modifiers, key)? {
+            check_shortcut_conflict(&shortcut, action, &config)?;
+        }
+        Ok(())
+    }
+}
+
+// 重新绑定可选的快捷键，按键为空时视为关闭
+fn rebind_optional_shortcut(
+    app: &AppHandle,
+    action: ShortcutAction,
+    modifiers: &[String],
+    key: &str,
+    config: &AppConfig,
+) -> Result<(), String> {
+    check_modifier_limit(modifiers, config.max_modifiers)?;
+    let new_shortcut = parse_optional_shortcut(modifiers, key)?;
+    if let Some(shortcut) = &new_shortcut {
+        check_shortcut_conflict(shortcut, action, config)?;
+    }
+
+    let Some(new_shortcut) = new_shortcut else {
+        // 关闭快捷键：只需注销旧的
+        if let Some(old_shortcut) = registered_shortcut(action) {
+            let _ = app.global_shortcut().unregister(old_shortcut);
+            set_registered_shortcut(action, None);
+        }
+        return Ok(());
+    };
+
+    // 注册失败时恢复旧的快捷键
+    let (registered, result) = swap_shortcut(
+        registered_shortcut(action),
+        new_shortcut,
+        |shortcut| {
+            let _ = app.global_shortcut().unregister(shortcut);
+        },
+        |shortcut| app.global_shortcut().register(shortcut).map_err(|e| e.to_string()),
+    );
+    set_registered_shortcut(action, registered);
+    result.map_err(|e| format!("注册快捷键失败: {}", e))
+}
+
+#[tauri::command]
+fn update_peek_shortcut(app: AppHandle, modifiers: Vec<String>, key: String) -> Result<String, String> {
+    bind_optional_shortcut(&app, ShortcutAction::Peek, &modifiers, &key)?;
+
+    // 持久化到文件
+    let mut config = load_config();
+    config.peek_shortcut_modifiers = modifiers.clone();
+    config.peek_shortcut_key = key.clone();
+    save_config(&config)?;
+
+    if key.is_empty() {
+        return Ok(String::new());
+    }
+    Ok(format_shortcut_display(&modifiers, &key))
+}
+
+#[tauri::command]
+fn update_pin_shortcut(app: AppHandle, modifiers: Vec<String>, key: String) -> Result<String, String> {
+    bind_optional_shortcut(&app, ShortcutAction::Pin, &modifiers, &key)?;
+
+    // 持久化到文件
+    let mut config = load_config();
+    config.pin_shortcut_modifiers = modifiers.clone();
+    config.pin_shortcut_key = key.clone();
+    save_config(&config)?;
+
+    if key.is_empty() {
+        return Ok(String::new());
+    }
+    Ok(format_shortcut_display(&modifiers, &key))
+}
+
+#[tauri::command]
+fn update_refresh_shortcut(app: AppHandle, modifiers: Vec<String>, key: String) -> Result<String, String> {
+    bind_optional_shortcut(&app, ShortcutAction::Refresh, &modifiers, &key)?;
+
+    // 持久化到文件
+    let mut config = load_config();
+    config.refresh_shortcut_modifiers = modifiers.clone();
+    config.refresh_shortcut_key = key.clone();
+    save_config(&config)?;
+
+    if key.is_empty() {
+        return Ok(String::new());
+    }
+    Ok(format_shortcut_display(&modifiers, &key))
+}
+
+// 切换置顶并通知前端同步状态
+fn toggle_pinned(app: &tauri::AppHandle) {
+    let pinned = !get_pinned();
+    if let Err(e) = set_pinned(pinned) {
+        log::warn!("{}", e);
+    }
+    let _ = app.emit("pinned", pinned);
+}
+
+// 速览状态迁移：Some(true) 显示，Some(false) 隐藏，None 保持不变
+// 按下时面板未显示才打开；松开时只隐藏由速览打开的面板，已通过快捷键或托盘打开的面板保持显示
+fn peek_transition(state: ShortcutState, visible: bool, opened_by_peek: bool) -> Option<bool> {
+    match state {
+        ShortcutState::Pressed if !visible => Some(true),
+        ShortcutState::Released if opened_by_peek => Some(false),
+        _ => None,
+    }
+}
+
+fn handle_peek(app: &tauri::AppHandle, state: ShortcutState) {
+    PEEKING.store(state == ShortcutState::Pressed, Ordering::SeqCst);
+    let visible = app
+        .get_webview_window(WINDOW_LABEL)
+        .and_then(|window| window.is_visible().ok())
+        .unwrap_or(false);
+    let opened_by_peek = if state == ShortcutState::Released {
+        PEEK_OPENED.swap(false, Ordering::SeqCst)
+    } else {
+        PEEK_OPENED.load(Ordering::SeqCst)
+    };
+    match peek_transition(state, visible, opened_by_peek) {
+        Some(true) => {
+            PEEK_OPENED.store(true, Ordering::SeqCst);
+            show_window(app, "shortcut");
+        }
+        Some(false) => hide_panel(app, "shortcut"),
+        None => {}
+    }
+}
+
+// 速览快捷键需要同时处理按下和松开，其余快捷键只在按下时触发
+fn shortcut_fires(action: ShortcutAction, state: ShortcutState) -> bool {
+    action == ShortcutAction::Peek || state == ShortcutState::Pressed
+}
+
+fn dispatch_shortcut(app: &tauri::AppHandle, action: ShortcutAction, state: ShortcutState) {
+    if !shortcut_fires(action, state) {
+        return;
+    }
+    match action {
+        ShortcutAction::Toggle => toggle_window(app, "shortcut"),
+        ShortcutAction::Settings => open_settings(app),
+        ShortcutAction::Peek => handle_peek(app, state),
+        ShortcutAction::Pin => toggle_pinned(app),
+        ShortcutAction::Refresh => request_refresh(app),
+        ShortcutAction::Hide => hide_panel(app, "shortcut"),
+        ShortcutAction::EmergencyReset => emergency_reset(app),
+    }
+}
+
+// 启动按接收顺序处理快捷键事件的线程，返回发送端
+fn spawn_shortcut_dispatcher(
+    mut handle: impl FnMut(ShortcutAction, ShortcutState) + Send + 'static,
+) -> Sender<(ShortcutAction, ShortcutState)> {
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        for (action, state) in rx {
+            handle(action, state);
+        }
+    });
+    tx
+}
+
+fn start_shortcut_dispatcher(app: AppHandle) {
+    let dispatcher = spawn_shortcut_dispatcher(move |action, state| dispatch_shortcut(&app, action, state));
+    if let Ok(mut slot) = SHORTCUT_EVENTS.lock() {
+        *slot = Some(dispatcher);
+    }
+}
+
+// 注册主快捷键（从配置加载）
+fn register_main_shortcut(app: &tauri::AppHandle, config: &AppConfig) -> Result<(), String> {
+    let code = parse_key(&config.shortcut_key).ok_or_else(|| format!("无效的按键: {}", config.shortcut_key))?;
+    let shortcut = Shortcut::new(parse_modifiers(&config.shortcut_modifiers), code);
+    app.global_shortcut()
+        .register(shortcut)
+        .map_err(|e| format!("注册快捷键失败: {}", e))?;
+    set_registered_shortcut(ShortcutAction::Toggle, Some(shortcut));
+    Ok(())
+}
+
+// 注册主快捷键及设置页、速览、置顶快捷键（未配置时跳过），返回主快捷键的注册结果
+fn register_user_shortcuts(app: &tauri::AppHandle, config: &AppConfig) -> Result<(), String> {
+    // 先注册主快捷键，可选快捷键不会占用它的组合
+    if registered_shortcut(ShortcutAction::Toggle).is_none() {
+        register_main_shortcut(app, config)?;
+    }
+    for (action, modifiers, key) in [
+        (ShortcutAction::Settings, &config.settings_shortcut_modifiers, &config.settings_shortcut_key),
+        (ShortcutAction::Peek, &config.peek_shortcut_modifiers, &config.peek_shortcut_key),
+        (ShortcutAction::Pin, &config.pin_shortcut_modifiers, &config.pin_shortcut_key),
+        (ShortcutAction::Refresh, &config.refresh_shortcut_modifiers, &config.refresh_shortcut_key),
+    ] {
+        let _ = rebind_optional_shortcut(app, action, modifiers, key, config);
+    }
+    Ok(())
+}
+
+const USER_SHORTCUT_ACTIONS: [ShortcutAction; 5] = [
+    ShortcutAction::Toggle,
+    ShortcutAction::Settings,
+    ShortcutAction::Peek,
+    ShortcutAction::Pin,
+    ShortcutAction::Refresh,
+];
+
+// 本次注册新增的快捷键：注册前未绑定、注册后已绑定的动作
+fn newly_registered(before: &[ShortcutAction], after: &[ShortcutAction]) -> Vec<ShortcutAction> {
+    after.iter().copied().filter(|action| !before.contains(action)).collect()
+}
+
+fn registered_user_actions() -> Vec<ShortcutAction> {
+    USER_SHORTCUT_ACTIONS
+        .into_iter()
+        .filter(|action| registered_shortcut(*action).is_some())
+        .collect()
+}
+
+// 注销用户快捷键，紧急重置快捷键与 Esc 不受影响
+fn unregister_user_shortcuts(app: &tauri::AppHandle) {
+    for action in USER_SHORTCUT_ACTIONS {
+        if let Some(shortcut) = registered_shortcut(action) {
+            let _ = app.global_shortcut().unregister(shortcut);
+            set_registered_shortcut(action, None);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum InteractionModel {
+    Hotkey,
+    Tray,
+}
+
+// 解析交互模式，未知值回退到快捷键模式
+fn parse_interaction_model(model: &str) -> InteractionModel {
+    match model {
+        "tray" => InteractionModel::Tray,
+        _ => InteractionModel::Hotkey,
+    }
+}
+
+// 交互模式对应的行为：是否注册全局快捷键、失焦时是否自动隐藏
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct InteractionEffects {
+    global_shortcuts: bool,
+    auto_hide: bool,
+}
+
+// 快捷键模式注册快捷键并失焦隐藏，仅托盘模式注销快捷键且不自动隐藏
+fn interaction_effects(model: InteractionModel) -> InteractionEffects {
+    match model {
+        InteractionModel::Hotkey => InteractionEffects { global_shortcuts: true, auto_hide: true },
+        InteractionModel::Tray => InteractionEffects { global_shortcuts: false, auto_hide: false },
+    }
+}
+
+// 应用交互模式
+fn apply_interaction_model(app: &tauri::AppHandle, config: &AppConfig, model: InteractionModel) -> Result<(), String> {
+    let effects = interaction_effects(model);
+    let previous_auto_hide = AUTO_HIDE.swap(effects.auto_hide, Ordering::SeqCst);
+    if effects.global_shortcuts {
+        // 注册失败时注销本次新注册的快捷键并恢复原来的失焦隐藏设置
+        let before = registered_user_actions();
+        let result = register_user_shortcuts(app, config);
+        if result.is_err() {
+            for action in newly_registered(&before, &registered_user_actions()) {
+                if let Some(shortcut) = registered_shortcut(action) {
+                    let _ = app.global_shortcut().unregister(shortcut);
+                    set_registered_shortcut(action, None);
+                }
+            }
+            AUTO_HIDE.store(previous_auto_hide, Ordering::SeqCst);
+        }
+        result
+    } else {
+        unregister_user_shortcuts(app);
+        Ok(())
+    }
+}
+
+#[tauri::command]
+fn set_interaction_model(app: AppHandle, model: String) -> Result<(), String> {
+    let parsed = match model.as_str() {
+        "hotkey" => InteractionModel::Hotkey,
+        "tray" => InteractionModel::Tray,
+        _ => return Err(format!("无效的交互模式: {}", model)),
+    };
+    let mut config = load_config();
+    check_tray_dependency(tray_available(), parsed, config.click_through)?;
+    apply_interaction_model(&app, &config, parsed)?;
+    config.interaction_model = model;
+    save_config(&config)
+}
+
+// 启动时各子系统的初始化结果
+#[derive(Debug, Clone, Serialize)]
+struct StartupReport {
+    window: bool,
+    tray: bool,
+    shortcut: bool,
+    errors: Vec<String>,
+}
+
+fn build_startup_report(
+    window: Result<(), String>,
+    tray: Result<(), String>,
+    shortcut: Result<(), String>,
+) -> StartupReport {
+    let errors = [("window", &window), ("tray", &tray), ("shortcut", &shortcut)]
+        .iter()
+        .filter_map(|(name, result)| result.as_ref().err().map(|e| format!("{}: {}", name, e)))
+        .collect();
+    StartupReport {
+        window: window.is_ok(),
+        tray: tray.is_ok(),
+        shortcut: shortcut.is_ok(),
+        errors,
+    }
+}
+
+#[tauri::command]
+fn startup_report() -> Option<StartupReport> {
+    STARTUP_REPORT.lock().ok()?.clone()
+}
+
+// 显示面板并通知前端跳转到设置页
+fn open_settings(app: &tauri::AppHandle) {
+    show_window(app, "shortcut");
+    let _ = app.emit("navigate", navigate_payload("settings"));
+}
+
+// navigate 事件的内容，前端按 route 跳转
+fn navigate_payload(route: &str) -> serde_json::Value {
+    serde_json::json!({ "route": route })
+}
+
+#[tauri::command]
+fn set_max_modifiers(max: Option<usize>) -> Result<(), String> {
+    let mut config = load_config();
+    config.max_modifiers = max;
+    save_config(&config)
+}
+
+#[tauri::command]
+fn set_time_format(app: AppHandle, format: String) -> Result<(), String> {
+    if format != "24h" && format != "12h" {
+        return Err(format!("无效的时间格式: {}", format));
+    }
+    let mut config = load_config();
+    config.time_format = format.clone();
+    save_config(&config)?;
+    let _ = app.emit("time-format", format);
+    Ok(())
+}
+
+// 按时间格式显示时分秒："24h" 为 "14:05:09"，"12h" 为 "02:05:09 PM"
+fn format_time(hour: u32, minute: u32, second: u32, time_format: &str) -> String {
+    if time_format == "12h" {
+        let period = if hour < 12 { "AM" } else { "PM" };
+        let hour = match hour % 12 {
+            0 => 12,
+            hour => hour,
+        };
+        format!("{:02}:{:02}:{:02} {}", hour, minute, second, period)
+    } else {
+        format!("{:02}:{:02}:{:02}", hour, minute, second)
+    }
+}
+
+// 托盘提示文字显示第一只股票的价格和行情的更新时间，时分秒由前端按本地时区传入
+#[tauri::command]
+fn update_tray_tooltip(
+    app: AppHandle,
+    name: String,
+    price: f64,
+    hour: u32,
+    minute: u32,
+    second: u32,
+) -> Result<(), String> {
+    let tray = app.tray_by_id(TRAY_ID).ok_or("托盘不存在")?;
+    let config = load_config();
+    let text = format!(
+        "{} {}\n更新于 {}",
+        name,
+        format_number(price, &config.locale),
+        format_time(hour, minute, second, &config.time_format)
+    );
+    tray.set_tooltip(Some(text)).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn set_locale(app: AppHandle, locale: String) -> Result<(), String> {
+    // 只做基本的格式校验，如 "en"、"en-US"
+    let valid = !locale.is_empty()
+        && locale.split('-').all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric()));
+    if !valid {
+        return Err(format!("无效的区域设置: {}", locale));
+    }
+    let mut config = load_config();
+    config.locale = locale.clone();
+    save_config(&config)?;
+    let _ = app.emit("locale", locale);
+    Ok(())
+}
+
+// 区域设置使用的千位分隔符和小数点，未列出的语言按 "1,234.56" 格式
+fn number_separators(locale: &str) -> (char, char) {
+    let language = locale.split('-').next().unwrap_or_default().to_ascii_lowercase();
+    match language.as_str() {
+        "de" | "es" | "it" | "nl" | "pt" | "id" | "tr" | "da" => ('.', ','),
+        "fr" | "ru" | "uk" | "pl" | "cs" | "sv" | "fi" | "nb" => ('\u{a0}', ','),
+        _ => (',', '.'),
+    }
+}
+
+// 按区域设置格式化数字，保留两位小数，如 en-US 为 "1,234.56"，de-DE 为 "1.234,56"
+fn format_number(value: f64, locale: &str) -> String {
+    if !value.is_finite() {
+        return value.to_string();
+    }
+    let (group, decimal) = number_separators(locale);
+    let fixed = format!("{:.2}", value.abs());
+    let (integer, fraction) = fixed.split_once('.').unwrap_or((&fixed, "00"));
+    let mut grouped = String::new();
+    for (i, digit) in integer.chars().enumerate() {
+        if i > 0 && (integer.len() - i) % 3 == 0 {
+            grouped.push(group);
+        }
+        grouped.push(digit);
+    }
+    // 四舍五入后为 0 时不显示负号
+    let sign = if value < 0.0 && fixed != "0.00" { "-" } else { "" };
+    format!("{}{}{}{}", sign, grouped, decimal, fraction)
+}
+
+fn is_valid_drag_region(region: &str) -> bool {
+    region == "whole" || region == "header"
+}
+
+// 拖动区域由前端标记，这里只负责保存并通知前端
+#[tauri::command]
+fn set_drag_region(app: AppHandle, region: String) -> Result<(), String> {
+    if !is_valid_drag_region(&region) {
+        return Err(format!("无效的拖动区域: {}", region));
+    }
+    let mut config = load_config();
+    config.drag_region = region.clone();
+    save_config(&config)?;
+    let _ = app.emit("drag-region", region);
+    Ok(())
+}
+
+fn clamp_resize_border(px: u32) -> u32 {
+    px.clamp(MIN_RESIZE_BORDER, MAX_RESIZE_BORDER)
+}
+
+// 缩放边框由前端绘制，这里只负责保存并通知前端，返回实际使用的宽度
+#[tauri::command]
+fn set_resize_border(app: AppHandle, px: u32) -> Result<u32, String> {
+    let px = clamp_resize_border(px);
+    let mut config = load_config();
+    config.resize_border_px = px;
+    save_config(&config)?;
+    let _ = app.emit("resize-border", px);
+    Ok(px)
+}
+
+// 窗口全部关闭引起的退出（code 为 None）按配置决定是否保留在托盘中运行，主动退出不拦截
+fn prevent_exit(code: Option<i32>, exit_on_last_window_close: bool) -> bool {
+    code.is_none() && !exit_on_last_window_close
+}
+
+#[tauri::command]
+fn set_exit_on_last_window_close(enabled: bool) -> Result<(), String> {
+    let mut config = load_config();
+    config.exit_on_last_window_close = enabled;
+    save_config(&config)
+}
+
+// 鼠标穿透开启后无法点击面板，需通过托盘菜单关闭，通过快捷键或托盘隐藏
+#[tauri::command]
+fn set_click_through(app: AppHandle, enabled: bool) -> Result<(), String> {
+    let mut config = load_config();
+    check_tray_dependency(tray_available(), parse_interaction_model(&config.interaction_model), enabled)?;
+    let window = app.get_webview_window(WINDOW_LABEL).ok_or("窗口不存在")?;
+    window.set_ignore_cursor_events(enabled).map_err(|e| e.to_string())?;
+    CLICK_THROUGH.store(enabled, Ordering::SeqCst);
+    config.click_through = enabled;
+    save_config(&config)?;
+
+    // 同步托盘菜单中的勾选状态
+    let _ = rebuild_tray_menu(app);
+    Ok(())
+}
+
+// 托盘菜单切换鼠标穿透
+fn toggle_click_through(app: &tauri::AppHandle) {
+    if let Err(e) = set_click_through(app.clone(), !CLICK_THROUGH.load(Ordering::SeqCst)) {
+        log::warn!("{}", e);
+    }
+}
+
+// 将不透明度限制在 0.1 ~ 1.0，非法数值视为不透明
+fn clamp_opacity(opacity: f64) -> f64 {
+    if opacity.is_finite() {
+        opacity.clamp(0.1, 1.0)
+    } else {
+        1.0
+    }
+}
+
+// 窗口本身没有不透明度接口，通过网页根元素的 CSS opacity 实现（窗口已是透明窗口）
+fn opacity_script(opacity: f64) -> String {
+    format!(
+        "(function () {{ var apply = function () {{ document.documentElement.style.opacity = '{}'; }}; \
+         if (document.documentElement) {{ apply(); }} else {{ document.addEventListener('DOMContentLoaded', apply); }} }})();",
+        clamp_opacity(opacity)
+    )
+}
+
+// 前端上报的本地时间（一天中的第几分钟）；后端没有时区信息，未上报前不启用夜间上限
+static LOCAL_MINUTE: Mutex<Option<u32>> = Mutex::new(None);
+
+// 解析 "HH:MM"，返回一天中的第几分钟
+fn parse_clock(text: &str) -> Option<u32> {
+    let (hour, minute) = text.trim().split_once(':')?;
+    let hour: u32 = hour.parse().ok()?;
+    let minute: u32 = minute.parse().ok()?;
+    if hour < 24 && minute < 60 {
+        Some(hour * 60 + minute)
+    } else {
+        None
+    }
+}
+
+// 时段为 [start, end)，start 大于 end 时跨午夜；start 等于 end 视为空时段
+fn in_night_window(minute: u32, start: u32, end: u32) -> bool {
+    if start <= end {
+        minute >= start && minute < end
+    } else {
+        minute >= start || minute < end
+    }
+}
+
+// 计算当前应使用的不透明度：夜间时段内取用户不透明度与夜间上限中较小的一个
+fn effective_opacity(config: &AppConfig, minute: Option<u32>) -> f64 {
+    let opacity = clamp_opacity(config.opacity);
+    let (Some(night), Some(minute)) = (config.night_opacity, minute) else {
+        return opacity;
+    };
+    match (parse_clock(&config.night_start), parse_clock(&config.night_end)) {
+        (Some(start), Some(end)) if in_night_window(minute, start, end) => opacity.min(clamp_opacity(night)),
+        _ => opacity,
+    }
+}
+
+fn current_opacity(config: &AppConfig) -> f64 {
+    effective_opacity(config, *LOCAL_MINUTE.lock().unwrap())
+}
+
+fn apply_current_opacity(app: &AppHandle, config: &AppConfig) -> Result<(), String> {
+    let window = app.get_webview_window(WINDOW_LABEL).ok_or("窗口不存在")?;
+    window.eval(opacity_script(current_opacity(config))).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn set_opacity(app: AppHandle, opacity: f64) -> Result<(), String> {
+    let mut config = load_config();
+    config.opacity = clamp_opacity(opacity);
+    apply_current_opacity(&app, &config)?;
+    save_config(&config)?;
+    Ok(())
+}
+
+#[tauri::command]
+fn set_night_opacity(app: AppHandle, opacity: Option<f64>, start: String, end: String) -> Result<(), String> {
+    if parse_clock(&start).is_none() {
+        return Err(format!("无效的时间: {}", start));
+    }
+    if parse_clock(&end).is_none() {
+        return Err(format!("无效的时间: {}", end));
+    }
+    let mut config = load_config();
+    config.night_opacity = opacity.map(clamp_opacity);
+    config.night_start = start.trim().to_string();
+    config.night_end = end.trim().to_string();
+    save_config(&config)?;
+    apply_current_opacity(&app, &config)
+}
+
+// 前端每分钟上报一次本地时间，进入或离开夜间时段时切换不透明度
+#[tauri::command]
+fn sync_night_opacity(app: AppHandle, hour: u32, minute: u32) -> Result<(), String> {
+    let config = load_config();
+    let before = current_opacity(&config);
+    *LOCAL_MINUTE.lock().unwrap() = Some((hour % 24) * 60 + minute % 60);
+    if current_opacity(&config) != before {
+        apply_current_opacity(&app, &config)?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn set_hover_expand(enabled: bool) -> Result<(), String> {
+    let mut config = load_config();
+    config.expand_on_hover = enabled;
+    save_config(&config)
+}
+
+// 根据悬停状态计算目标尺寸，未启用时返回 None
+fn hover_target_size(config: &AppConfig, entered: bool) -> Option<(f64, f64)> {
+    if !config.expand_on_hover {
+        return None;
+    }
+    if entered {
+        Some((config.hover_expand_width, config.hover_expand_height))
+    } else {
+        Some((config.window_width, config.window_height))
+    }
+}
+
+// 防抖结束后应调整到的尺寸：防抖期间又有新的悬停事件（superseded）、未启用或展开状态未变化时返回 None
+fn hover_resize(config: &AppConfig, entered: bool, superseded: bool, expanded: bool) -> Option<(f64, f64)> {
+    if superseded || expanded == entered {
+        return None;
+    }
+    hover_target_size(config, entered)
+}
+
+#[tauri::command]
+fn hover_changed(app: AppHandle, entered: bool) {
+    let generation = HOVER_GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    std::thread::spawn(move || {
+        std::thread::sleep(HOVER_DEBOUNCE);
+        let superseded = HOVER_GENERATION.load(Ordering::SeqCst) != generation;
+        let expanded = HOVER_EXPANDED.load(Ordering::SeqCst);
+        let Some((width, height)) = hover_resize(&load_config(), entered, superseded, expanded) else {
+            return;
+        };
+        HOVER_EXPANDED.store(entered, Ordering::SeqCst);
+        if let Some(window) = app.get_webview_window(WINDOW_LABEL) {
+            resize_keep_anchor(&window, width, height);
+        }
+    });
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum LayoutMode {
+    Compact,
+    Dot,
+}
+
+// 解析保存的布局模式，未知值回退到普通面板
+fn parse_layout_mode(mode: &str) -> LayoutMode {
+    match mode {
+        "dot" => LayoutMode::Dot,
+        _ => LayoutMode::Compact,
+    }
+}
+
+fn layout_mode_name(mode: LayoutMode) -> &'static str {
+    match mode {
+        LayoutMode::Compact => "compact",
+        LayoutMode::Dot => "dot",
+    }
+}
+
+// 圆点模式切换后的窗口尺寸：进入时为固定的圆点尺寸，退出时恢复保存的尺寸
+// 圆点模式期间的尺寸变化不写入配置，因此保存的尺寸即进入前的尺寸
+fn dot_mode_size(config: &AppConfig, entering: bool) -> (f64, f64) {
+    if entering {
+        (DOT_SIZE, DOT_SIZE)
+    } else {
+        (config.window_width, config.window_height)
+    }
+}
+
+// 进入或退出圆点模式并调整窗口尺寸
+fn apply_dot_mode(window: &WebviewWindow, config: &AppConfig, entering: bool) {
+    let (width, height) = dot_mode_size(config, entering);
+    if entering {
+        DOT_MODE.store(true, Ordering::SeqCst);
+        let _ = window.set_min_size(None::<LogicalSize<f64>>);
+        resize_keep_anchor(window, width, height);
+    } else {
+        resize_keep_anchor(window, width, height);
+        let _ = window.set_min_size(Some(LogicalSize::new(WINDOW_WIDTH, WINDOW_HEIGHT)));
+        DOT_MODE.store(false, Ordering::SeqCst);
+    }
+}
+
+#[tauri::command]
+fn toggle_dot_mode(app: AppHandle) -> Result<bool, String> {
+    let window = app.get_webview_window(WINDOW_LABEL).ok_or("窗口不存在")?;
+    let entering = !DOT_MODE.load(Ordering::SeqCst);
+    let mut config = load_config();
+
+    apply_dot_mode(&window, &config, entering);
+
+    let mode = if entering { LayoutMode::Dot } else { LayoutMode::Compact };
+    config.last_layout_mode = layout_mode_name(mode).to_string();
+    save_config(&config)?;
+    let _ = app.emit("dot-mode", entering);
+    Ok(entering)
+}
+
+// 预设宽高比
+fn aspect_ratio(name: &str) -> Option<f64> {
+    match name {
+        "square" => Some(1.0),
+        "16:9" => Some(16.0 / 9.0),
+        "4:3" => Some(4.0 / 3.0),
+        "ultrawide" => Some(21.0 / 9.0),
+        _ => None,
+    }
+}
+
+// 按预设宽高比计算尺寸：保持宽度，高度低于最小值时按比例放大宽度
+fn aspect_preset_size(name: &str, width: f64) -> Option<(f64, f64)> {
+    let ratio = aspect_ratio(name)?;
+    let height = width / ratio;
+    if height < WINDOW_HEIGHT {
+        Some((WINDOW_HEIGHT * ratio, WINDOW_HEIGHT))
+    } else {
+        Some((width, height))
+    }
+}
+
+#[tauri::command]
+fn apply_aspect_preset(app: AppHandle, name: String) -> Result<(f64, f64), String> {
+    let window = app.get_webview_window(WINDOW_LABEL).ok_or("窗口不存在")?;
+    let scale = window.scale_factor().unwrap_or(1.0);
+    let current_width = window
+        .inner_size()
+        .map(|size| size.width as f64 / scale)
+        .unwrap_or(WINDOW_WIDTH);
+    let (width, height) =
+        aspect_preset_size(&name, current_width).ok_or_else(|| format!("未知的比例预设: {}", name))?;
+
+    resize_keep_anchor(&window, width, height);
+
+    let mut config = load_config();
+    config.window_width = width;
+    config.window_height = height;
+    save_config(&config)?;
+    Ok((width, height))
+}
+
+// 窗口在屏幕上的物理像素区域
+#[derive(Debug, Clone, Serialize)]
+struct Rect {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    scale_factor: f64,
+}
+
+fn physical_rect(position: PhysicalPosition<i32>, size: PhysicalSize<u32>, scale_factor: f64) -> Rect {
+    Rect {
+        x: position.x,
+        y: position.y,
+        width: size.width,
+        height: size.height,
+        scale_factor,
+    }
+}
+
+#[tauri::command]
+fn window_rect_physical(app: AppHandle) -> Result<Rect, String> {
+    let window = app.get_webview_window(WINDOW_LABEL).ok_or("窗口不存在")?;
+    let position = window.outer_position().map_err(|e| e.to_string())?;
+    let size = window.outer_size().map_err(|e| e.to_string())?;
+    let scale_factor = window.scale_factor().map_err(|e| e.to_string())?;
+    Ok(physical_rect(position, size, scale_factor))
+}
+
+fn apply_geometry(window: &WebviewWindow, geometry: &Geometry) {
+    let _ = window.set_size(LogicalSize::new(geometry.width, geometry.height));
+    move_window(window, PhysicalPosition::new(geometry.x, geometry.y));
+}
+
+#[tauri::command]
+fn save_tour(name: String, geometries: Vec<Geometry>) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("巡回名称不能为空".to_string());
+    }
+    if geometries.is_empty() {
+        return Err("巡回至少需要一个位置".to_string());
+    }
+    let mut config = load_config();
+    config.tours.insert(name.clone(), geometries);
+    save_config(&config)?;
+
+    // 重新保存后从第一步开始
+    if let Ok(mut steps) = TOUR_STEPS.lock() {
+        steps.get_or_insert_with(HashMap::new).remove(&name);
+    }
+    Ok(())
+}
+
+// 当前窗口的几何信息
+fn current_geometry(window: &WebviewWindow) -> Option<Geometry> {
+    let scale = window.scale_factor().ok()?;
+    let position = window.outer_position().ok()?;
+    let size = window.inner_size().ok()?;
+    Some(Geometry {
+        x: position.x,
+        y: position.y,
+        width: size.width as f64 / scale,
+        height: size.height as f64 / scale,
+    })
+}
+
+// 将几何信息限制在显示器范围内（monitor_pos/monitor_size 为物理像素）
+fn clamp_geometry_to_monitor(
+    geometry: &Geometry,
+    monitor_pos: PhysicalPosition<i32>,
+    monitor_size: PhysicalSize<u32>,
+    scale: f64,
+) -> Geometry {
+    let width = (geometry.width * scale) as i32;
+    let height = (geometry.height * scale) as i32;
+    let max_x = (monitor_pos.x + monitor_size.width as i32 - width).max(monitor_pos.x);
+    let max_y = (monitor_pos.y + monitor_size.height as i32 - height).max(monitor_pos.y);
+    Geometry {
+        x: geometry.x.clamp(monitor_pos.x, max_x),
+        y: geometry.y.clamp(monitor_pos.y, max_y),
+        ..geometry.clone()
+    }
+}
+
+#[tauri::command]
+fn set_home(app: AppHandle) -> Result<Geometry, String> {
+    let window = app.get_webview_window(WINDOW_LABEL).ok_or("窗口不存在")?;
+    let geometry = current_geometry(&window).ok_or("无法获取窗口位置")?;
+    let mut config = load_config();
+    config.home = Some(geometry.clone());
+    save_config(&config)?;
+    Ok(geometry)
+}
+
+#[tauri::command]
+fn go_home(app: AppHandle) -> Result<Geometry, String> {
+    let window = app.get_webview_window(WINDOW_LABEL).ok_or("窗口不存在")?;
+    let mut config = load_config();
+    let home = config.home.clone().ok_or("尚未设置原位")?;
+
+    // 原位所在显示器已断开时放到主显示器上
+    let monitors = window.available_monitors().map_err(|e| e.to_string())?;
+    let point = PhysicalPosition::new(home.x as f64, home.y as f64);
+    let monitor = pick_monitor(&monitors, &[Some(point)])
+        .or_else(|| window.primary_monitor().ok().flatten())
+        .ok_or("找不到可用的显示器")?;
+    let geometry = clamp_geometry_to_monitor(&home, *monitor.position(), *monitor.size(), monitor.scale_factor());
+    apply_geometry(&window, &geometry);
+
+    // 回到原位后以原位作为拖动后的位置和保存的大小
+    if config.persist_manual_position {
+        config.x = Some(geometry.x);
+        config.y = Some(geometry.y);
+    } else {
+        set_session_position(Some((geometry.x, geometry.y)));
+    }
+    config.window_width = geometry.width;
+    config.window_height = geometry.height;
+    save_config(&config)?;
+    Ok(geometry)
+}
+
+// 取巡回的下一步：走完最后一步后返回 None 并回到开头
+fn advance_tour(len: usize, step: usize) -> (Option<usize>, usize) {
+    if step < len {
+        (Some(step), step + 1)
+    } else {
+        (None, 0)
+    }
+}
+
+#[tauri::command]
+fn next_tour_step(app: AppHandle, name: String) -> Result<Option<Geometry>, String> {
+    let config = load_config();
+    let tour = config.tours.get(&name).ok_or_else(|| format!("巡回不存在: {}", name))?;
+
+    let mut steps = TOUR_STEPS.lock().map_err(|e| e.to_string())?;
+    let step = steps.get_or_insert_with(HashMap::new).entry(name).or_insert(0);
+    let (index, next) = advance_tour(tour.len(), *step);
+    *step = next;
+
+    let geometry = index.map(|i| tour[i].clone());
+    if let (Some(geometry), Some(window)) = (&geometry, app.get_webview_window(WINDOW_LABEL)) {
+        apply_geometry(&window, geometry);
+    }
+    Ok(geometry)
+}
+
+// 详情气泡展开后的宽度，不超过显示器内可用的宽度
+fn bubble_width(base_width: f64, extra: f64, max_width: f64) -> f64 {
+    (base_width + extra.max(0.0)).min(max_width).max(base_width.min(max_width))
+}
+
+#[tauri::command]
+fn show_detail_bubble(app: AppHandle, width: f64) -> Result<(), String> {
+    let window = app.get_webview_window(WINDOW_LABEL).ok_or("窗口不存在")?;
+    let scale = window.scale_factor().unwrap_or(1.0);
+    let size = window.inner_size().map_err(|e| e.to_string())?;
+    let (logical_width, logical_height) = (size.width as f64 / scale, size.height as f64 / scale);
+
+    // 已展开时以展开前的宽度为基准；调整尺寸前释放锁，Resized 回调中还会读取
+    let base_width = *BUBBLE_BASE_WIDTH
+        .lock()
+        .map_err(|e| e.to_string())?
+        .get_or_insert(logical_width);
+
+    // 锚定的边缘固定，展开时不能超出显示器
+    let anchor = parse_anchor(&load_config().anchor).unwrap_or(Anchor::TopRight);
+    let max_width = match (window.outer_position(), window.outer_size(), window.current_monitor()) {
+        (Ok(pos), Ok(outer), Ok(Some(monitor))) => {
+            let monitor_left = monitor.position().x;
+            let monitor_right = monitor_left + monitor.size().width as i32;
+            let available = match anchor {
+                Anchor::TopRight | Anchor::BottomRight => pos.x + outer.width as i32 - monitor_left,
+                Anchor::TopLeft | Anchor::BottomLeft => monitor_right - pos.x,
+                Anchor::Center => monitor_right - monitor_left,
+            };
+            available as f64 / scale
+        }
+        _ => f64::MAX,
+    };
+    resize_keep_anchor(&window, bubble_width(base_width, width, max_width), logical_height);
+    Ok(())
+}
+
+#[tauri::command]
+fn hide_detail_bubble(app: AppHandle) -> Result<(), String> {
+    let window = app.get_webview_window(WINDOW_LABEL).ok_or("窗口不存在")?;
+    let base_width = BUBBLE_BASE_WIDTH.lock().map_err(|e| e.to_string())?.take();
+    if let Some(base_width) = base_width {
+        let scale = window.scale_factor().unwrap_or(1.0);
+        let height = window
+            .inner_size()
+            .map(|size| size.height as f64 / scale)
+            .unwrap_or(WINDOW_HEIGHT);
+        resize_keep_anchor(&window, base_width, height);
+    }
+    Ok(())
+}
+
+// 重绘时的位移：先移动 1 像素再移回原位
+fn nudge_positions(position: PhysicalPosition<i32>) -> [PhysicalPosition<i32>; 2] {
+    [PhysicalPosition::new(position.x + 1, position.y), position]
+}
+
+// 部分平台切换主题后窗口不会重绘，移动一下窗口强制重绘；窗口不存在时不做任何事
+#[tauri::command]
+fn force_redraw(app: AppHandle) {
+    if let Some(window) = app.get_webview_window(WINDOW_LABEL) {
+        if let Ok(position) = window.outer_position() {
+            for step in nudge_positions(position) {
+                move_window(&window, step);
+            }
+        }
+    }
+}
+
+// 按新的缩放比例换算保存的逻辑尺寸，保证跨显示器移动后逻辑尺寸不变
+fn scaled_physical_size(width: f64, height: f64, scale_factor: f64) -> PhysicalSize<u32> {
+    PhysicalSize::new(
+        (width * scale_factor).round() as u32,
+        (height * scale_factor).round() as u32,
+    )
+}
+
+#[tauri::command]
+fn set_track_dpi(enabled: bool) -> Result<(), String> {
+    let mut config = load_config();
+    config.track_dpi = enabled;
+    save_config(&config)
+}
+
+// 是否处于临时布局（悬停展开、圆点模式、详情气泡），此时不保存窗口尺寸
+fn transient_layout_active() -> bool {
+    HOVER_EXPANDED.load(Ordering::SeqCst)
+        || DOT_MODE.load(Ordering::SeqCst)
+        || SMALL_SCREEN.load(Ordering::SeqCst)
+        || BUBBLE_BASE_WIDTH.lock().map(|base| base.is_some()).unwrap_or(false)
+}
+
+// 调整窗口大小并保持锚定的边缘不动
+fn resize_keep_anchor(window: &WebviewWindow, width: f64, height: f64) {
+    let anchor = parse_anchor(&load_config().anchor).unwrap_or(Anchor::TopRight);
+    let scale = window.scale_factor().unwrap_or(1.0);
+    let old_rect = window.outer_position().ok().zip(window.outer_size().ok());
+    let _ = window.set_size(LogicalSize::new(width, height));
+    if let Some((pos, size)) = old_rect {
+        let new_size = PhysicalSize::new((width * scale) as u32, (height * scale) as u32);
+        move_window(window, anchored_origin(anchor, pos, size, new_size));
+    }
+}
+
+// 可导入导出的快捷键绑定
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ShortcutBinding {
+    action: String,
+    shortcut: String,
+}
+
+// 生成可移植的快捷键字符串，如 "Alt+Shift+M"
+fn format_portable_shortcut(modifiers: &[String], key: &str) -> String {
+    if key.is_empty() {
+        return String::new();
+    }
+    let mut parts: Vec<&str> = modifiers.iter().map(String::as_str).collect();
+    parts.push(key);
+    parts.join("+")
+}
+
+// 解析可移植的快捷键字符串，空字符串表示未绑定
+fn parse_portable_shortcut(s: &str) -> Option<(Vec<String>, String)> {
+    let s = s.trim();
+    if s.is_empty() {
+        return Some((Vec::new(), String::new()));
+    }
+    let mut parts: Vec<String> = s.split('+').map(|p| p.trim().to_string()).collect();
+    let key = parts.pop()?;
+    parse_key(&key)?;
+    // 修饰键必须全部可识别
+    for m in &parts {
+        if parse_modifiers(std::slice::from_ref(m))?.is_empty() {
+            return None;
+        }
+    }
+    Some((parts, key))
+}
+
+#[tauri::command]
+fn export_shortcuts() -> String {
+    serde_json::to_string_pretty(&shortcut_bindings(&load_config())).unwrap_or_else(|_| "[]".to_string())
+}
+
+// 配置中的全部快捷键绑定，未启用的绑定为空字符串
+fn shortcut_bindings(config: &AppConfig) -> Vec<ShortcutBinding> {
+    vec![
+        ShortcutBinding {
+            action: "toggle".to_string(),
+            shortcut: format_portable_shortcut(&config.shortcut_modifiers, &config.shortcut_key),
+        },
+        ShortcutBinding {
+            action: "settings".to_string(),
+            shortcut: format_portable_shortcut(&config.settings_shortcut_modifiers, &config.settings_shortcut_key),
+        },
+        ShortcutBinding {
+            action: "peek".to_string(),
+            shortcut: format_portable_shortcut(&config.peek_shortcut_modifiers, &config.peek_shortcut_key),
+        },
+        ShortcutBinding {
+            action: "pin".to_string(),
+            shortcut: format_portable_shortcut(&config.pin_shortcut_modifiers, &config.pin_shortcut_key),
+        },
+        ShortcutBinding {
+            action: "refresh".to_string(),
+            shortcut: format_portable_shortcut(&config.refresh_shortcut_modifiers, &config.refresh_shortcut_key),
+        },
+    ]
+}
+
+#[tauri::command]
+fn import_shortcuts(app: AppHandle, json: String) -> Result<(), String> {
+    // 先全部校验，任何一条无效或彼此重复都不做修改
+    let parsed = parse_shortcut_bindings(&json)?;
+    let previous = load_config();
+    let updated = with_shortcut_bindings(&previous, parsed);
+    check_imported_shortcuts(&updated)?;
+
+    replace_user_shortcuts(&app, &previous, &updated)?;
+    let _ = rebuild_tray_menu(app);
+    Ok(())
+}
+
+// 把导入的快捷键绑定写入配置副本，未出现的动作保持原绑定
+fn with_shortcut_bindings(config: &AppConfig, bindings: Vec<(String, Vec<String>, String)>) -> AppConfig {
+    let mut updated = config.clone();
+    for (action, modifiers, key) in bindings {
+        let (target_modifiers, target_key) = match action.as_str() {
+            "toggle" => (&mut updated.shortcut_modifiers, &mut updated.shortcut_key),
+            "settings" => (&mut updated.settings_shortcut_modifiers, &mut updated.settings_shortcut_key),
+            "peek" => (&mut updated.peek_shortcut_modifiers, &mut updated.peek_shortcut_key),
+            "pin" => (&mut updated.pin_shortcut_modifiers, &mut updated.pin_shortcut_key),
+            "refresh" => (&mut updated.refresh_shortcut_modifiers, &mut updated.refresh_shortcut_key),
+            _ => continue,
+        };
+        *target_modifiers = modifiers;
+        *target_key = key;
+    }
+    updated
+}
+
+// 解析导入的快捷键绑定，返回 (动作, 修饰键, 按键)；任何一条无效都返回错误
+fn parse_shortcut_bindings(json: &str) -> Result<Vec<(String, Vec<String>, String)>, String> {
+    let bindings: Vec<ShortcutBinding> =
+        serde_json::from_str(json).map_err(|e| format!("快捷键配置格式错误: {}", e))?;
+    let mut parsed = Vec::new();
+    for binding in &bindings {
+        // 兼容界面上显示的符号形式，如 "⌥M"
+        let (modifiers, key) = parse_portable_shortcut(&binding.shortcut)
+            .or_else(|| parse_shortcut_display(binding.shortcut.trim()))
+            .ok_or_else(|| format!("无效的快捷键: {}", binding.shortcut))?;
+        match binding.action.as_str() {
+            "toggle" if key.is_empty() => return Err("显示面板快捷键不能为空".to_string()),
+            "toggle" | "settings" | "peek" | "pin" | "refresh" => {}
+            other => return Err(format!("未知的快捷键动作: {}", other)),
+        }
+        parsed.push((binding.action.clone(), modifiers, key));
+    }
+    Ok(parsed)
+}
+
+#[tauri::command]
+fn save_window_size(width: f64, height: f64) -> Result<(), String> {
+    let mut config = load_config();
+    config.window_width = width;
+    config.window_height = height;
+    save_config(&config)
+}
+
+fn format_shortcut_display(modifiers: &[String], key: &str) -> String {
+    let mut parts = Vec::new();
+    for m in modifiers {
+        match m.to_uppercase().as_str() {
+            "ALT" | "OPTION" => parts.push("⌥"),
+            "CTRL" | "CONTROL" => parts.push("⌃"),
+            "SHIFT" => parts.push("⇧"),
+            "META" | "COMMAND" | "CMD" | "SUPER" => parts.push("⌘"),
+            _ => {}
+        }
+    }
+    parts.push(key);
+    parts.join("")
+}
+
+// format_shortcut_display 的逆操作：把 "⌥⇧M" 解析回 (["Alt", "Shift"], "M")
+fn parse_shortcut_display(display: &str) -> Option<(Vec<String>, String)> {
+    let mut modifiers = Vec::new();
+    let mut rest = display;
+    while let Some(c) = rest.chars().next() {
+        let name = match c {
+            '⌥' => "Alt",
+            '⌃' => "Ctrl",
+            '⇧' => "Shift",
+            '⌘' => "Meta",
+            _ => break,
+        };
+        modifiers.push(name.to_string());
+        rest = &rest[c.len_utf8()..];
+    }
+    parse_key(rest)?;
+    Some((modifiers, rest.to_string()))
+}
+
+#[tauri::command]
+fn set_show_on_active_monitor(enabled: bool) -> Result<(), String> {
+    let mut config = load_config();
+    config.show_on_active_monitor = enabled;
+    save_config(&config)
+}
+
+// 判断物理坐标是否落在显示器范围内
+fn monitor_contains(pos: PhysicalPosition<i32>, size: PhysicalSize<u32>, point: &PhysicalPosition<f64>) -> bool {
+    point.x >= pos.x as f64
+        && point.x < pos.x as f64 + size.width as f64
+        && point.y >= pos.y as f64
+        && point.y < pos.y as f64 + size.height as f64
+}
+
+// 按优先级依次取候选坐标所在的显示器，返回其序号；都不匹配时返回 None，由调用方回退到主显示器
+fn pick_monitor_index(
+    bounds: &[(PhysicalPosition<i32>, PhysicalSize<u32>)],
+    points: &[Option<PhysicalPosition<f64>>],
+) -> Option<usize> {
+    points
+        .iter()
+        .flatten()
+        .find_map(|point| bounds.iter().position(|&(pos, size)| monitor_contains(pos, size, point)))
+}
+
+fn pick_monitor(monitors: &[Monitor], points: &[Option<PhysicalPosition<f64>>]) -> Option<Monitor> {
+    let bounds: Vec<_> = monitors.iter().map(|m| (*m.position(), *m.size())).collect();
+    pick_monitor_index(&bounds, points).map(|index| monitors[index].clone())
+}
+
+// 活动显示器：Tauri 不提供前台窗口位置，因此以鼠标所在显示器为准
+fn active_monitor(window: &WebviewWindow) -> Option<Monitor> {
+    let monitors = window.available_monitors().ok()?;
+    let cursor = window.cursor_position().ok();
+    pick_monitor(&monitors, &[cursor])
+}
+
+// 选择显示面板的显示器（鼠标所在显示器 -> 主显示器）
+fn target_monitor(window: &WebviewWindow, config: &AppConfig) -> Option<Monitor> {
+    let monitor = if config.show_on_active_monitor {
+        active_monitor(window)
+    } else {
+        None
+    };
+    monitor.or_else(|| window.primary_monitor().ok().flatten())
+}
+
+// 可用区域（逻辑像素）过小时返回应使用的小屏幕尺寸
+fn small_screen_size(work_width: f64, work_height: f64, config: &AppConfig) -> Option<(f64, f64)> {
+    if work_width < SMALL_SCREEN_MAX_WIDTH || work_height < SMALL_SCREEN_MAX_HEIGHT {
+        Some((config.small_screen_width, config.small_screen_height))
+    } else {
+        None
+    }
+}
+
+// 按目标显示器大小切换小屏幕尺寸，离开小屏幕时恢复保存的尺寸
+fn apply_screen_size(window: &WebviewWindow, config: &AppConfig) {
+    // 圆点模式、悬停展开时保持当前尺寸
+    if DOT_MODE.load(Ordering::SeqCst) || HOVER_EXPANDED.load(Ordering::SeqCst) {
+        return;
+    }
+    let Some(monitor) = target_monitor(window, config) else {
+        return;
+    };
+    let work_area = monitor.work_area().size.to_logical::<f64>(monitor.scale_factor());
+    match small_screen_size(work_area.width, work_area.height, config) {
+        Some((width, height)) => {
+            SMALL_SCREEN.store(true, Ordering::SeqCst);
+            let _ = window.set_min_size(Some(LogicalSize::new(width.min(WINDOW_WIDTH), height.min(WINDOW_HEIGHT))));
+            let _ = window.set_size(LogicalSize::new(width, height));
+        }
+        None if SMALL_SCREEN.load(Ordering::SeqCst) => {
+            let _ = window.set_size(LogicalSize::new(config.window_width, config.window_height));
+            let _ = window.set_min_size(Some(LogicalSize::new(WINDOW_WIDTH, WINDOW_HEIGHT)));
+            SMALL_SCREEN.store(false, Ordering::SeqCst);
+        }
+        None => {}
+    }
+}
+
+#[tauri::command]
+fn set_small_screen_size(width: f64, height: f64) -> Result<(), String> {
+    let mut config = load_config();
+    config.small_screen_width = clamp_dimension(width, DOT_SIZE);
+    config.small_screen_height = clamp_dimension(height, DOT_SIZE);
+    save_config(&config)
+}
+
+// 创建托盘菜单并绑定事件 - 左键点击直接显示菜单
+fn setup_tray(app: &tauri::AppHandle, config: &AppConfig) -> Result<(), String> {
+    // 获取配置中的 tray icon，托盘不存在时仍可通过快捷键使用
+    let tray = app
+        .tray_by_id(TRAY_ID)
+        .ok_or_else(|| format!("托盘图标 \"{}\" 不存在", TRAY_ID))?;
+    let menu = build_tray_menu(app, config).map_err(|e| e.to_string())?;
+    tray.set_menu(Some(menu)).map_err(|e| e.to_string())?;
+    tray.set_show_menu_on_left_click(true).map_err(|e| e.to_string())?;
+    tray.on_menu_event(|app, event| {
+        match event.id.as_ref() {
+            "show" => show_window(app, "tray"),
+            "click_through" => toggle_click_through(app),
+            "quit" => app.exit(0),
+            _ => {}
+        }
+    });
+    Ok(())
+}
+
+#[tauri::command]
+fn tray_available() -> bool {
+    TRAY_AVAILABLE.load(Ordering::SeqCst)
+}
+
+// 托盘不可用时拒绝依赖托盘的设置：仅托盘模式下无法再打开面板，鼠标穿透开启后无法再关闭
+fn check_tray_dependency(tray_available: bool, model: InteractionModel, click_through: bool) -> Result<(), String> {
+    if tray_available {
+        return Ok(());
+    }
+    if model == InteractionModel::Tray {
+        return Err("托盘不可用，无法切换到仅托盘模式".to_string());
+    }
+    if click_through {
+        return Err("托盘不可用，无法开启鼠标穿透".to_string());
+    }
+    Ok(())
+}
+
+// 托盘菜单中“显示面板”项的文字
+fn tray_show_label(modifiers: &[String], key: &str) -> String {
+    format!("显示面板 ({})", format_shortcut_display(modifiers, key))
+}
+
+fn build_tray_menu(app: &tauri::AppHandle, config: &AppConfig) -> tauri::Result<Menu<tauri::Wry>> {
+    let show_label = tray_show_label(&config.shortcut_modifiers, &config.shortcut_key);
+    let show_item = MenuItem::with_id(app, "show", show_label, true, None::<&str>)?;
+    let click_through_item =
+        CheckMenuItem::with_id(app, "click_through", "鼠标穿透", true, CLICK_THROUGH.load(Ordering::SeqCst), None::<&str>)?;
+    let quit_item = MenuItem::with_id(app, "quit", "退出", true, None::<&str>)?;
+    Menu::with_items(app, &[&show_item, &click_through_item, &quit_item])
+}
+
+// 按当前配置重建托盘菜单（快捷键变化后菜单文字需要同步）
+#[tauri::command]
+fn rebuild_tray_menu(app: AppHandle) -> Result<(), String> {
+    let tray = app.tray_by_id(TRAY_ID).ok_or("托盘不存在")?;
+    let menu = build_tray_menu(&app, &load_config()).map_err(|e| e.to_string())?;
+    tray.set_menu(Some(menu)).map_err(|e| e.to_string())
+}
+
+// 延迟置顶的时长，0 表示立即置顶
+fn always_on_top_delay(delay_ms: u64) -> Option<Duration> {
+    if delay_ms == 0 {
+        None
+    } else {
+        Some(Duration::from_millis(delay_ms))
+    }
+}
+
+#[tauri::command]
+fn set_min_write_interval(ms: u64) -> Result<(), String> {
+    let mut config = load_config();
+    config.min_write_interval_ms = ms;
+    save_config(&config)
+}
+
+#[tauri::command]
+fn set_always_on_top_delay(delay_ms: u64) -> Result<(), String> {
+    let mut config = load_config();
+    config.always_on_top_delay_ms = delay_ms;
+    save_config(&config)
+}
+
+// 程序主动移动窗口，并记录位置以便 Moved 事件中与用户拖动区分
+fn move_window(window: &WebviewWindow, position: PhysicalPosition<i32>) {
+    if let Ok(mut moves) = PROGRAMMATIC_MOVES.lock() {
+        moves.push_back((position.x, position.y));
+        while moves.len() > PROGRAMMATIC_MOVES_CAP {
+            moves.pop_front();
+        }
+    }
+    let _ = window.set_position(position);
+}
+
+// 若该位置来自程序主动移动则消费记录并返回 true
+fn take_programmatic_move(position: PhysicalPosition<i32>) -> bool {
+    let Ok(mut moves) = PROGRAMMATIC_MOVES.lock() else {
+        return false;
+    };
+    match moves.iter().position(|&(x, y)| x == position.x && y == position.y) {
+        Some(index) => {
+            moves.remove(index);
+            true
+        }
+        None => false,
+    }
+}
+
+// 拖动后的位置：保存拖动位置时取配置中的位置，否则取本次运行中的位置
+fn manual_position(config: &AppConfig, session: Option<(i32, i32)>) -> Option<(i32, i32)> {
+    if config.persist_manual_position {
+        config.x.zip(config.y)
+    } else {
+        session
+    }
+}
+
+fn session_position() -> Option<(i32, i32)> {
+    *SESSION_POSITION.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn set_session_position(position: Option<(i32, i32)>) {
+    *SESSION_POSITION.lock().unwrap_or_else(PoisonError::into_inner) = position;
+}
+
+// 窗口移动的处理：None 忽略（程序主动移动、临时布局或窗口隐藏），
+// Some(true) 写入配置，Some(false) 只记为本次运行的位置
+fn persist_move(programmatic: bool, transient: bool, visible: bool, persist_manual_position: bool) -> Option<bool> {
+    if programmatic || transient || !visible {
+        return None;
+    }
+    Some(persist_manual_position)
+}
+
+#[tauri::command]
+fn set_persist_manual_position(enabled: bool) -> Result<(), String> {
+    // 切换时把当前拖动位置转移到对应的保存方式，关闭后下次启动回到锚点
+    let mut config = load_config();
+    if enabled {
+        if let Some((x, y)) = session_position() {
+            config.x = Some(x);
+            config.y = Some(y);
+        }
+    } else {
+        set_session_position(config.x.zip(config.y));
+        config.x = None;
+        config.y = None;
+    }
+    config.persist_manual_position = enabled;
+    save_config(&config)
+}
+
+// 拖动后的窗口位置，不在任何显示器范围内时（如显示器已断开）忽略
+fn saved_position(window: &WebviewWindow, config: &AppConfig) -> Option<PhysicalPosition<i32>> {
+    let (x, y) = manual_position(config, session_position())?;
+    let monitors = window.available_monitors().ok()?;
+    let point = PhysicalPosition::new(x as f64, y as f64);
+    pick_monitor(&monitors, &[Some(point)])?;
+    Some(PhysicalPosition::new(x, y))
+}
+
+// 面板锚定位置
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Anchor {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+    Center,
+}
+
+fn parse_anchor(anchor: &str) -> Option<Anchor> {
+    match anchor {
+        "top-left" => Some(Anchor::TopLeft),
+        "top-right" => Some(Anchor::TopRight),
+        "bottom-left" => Some(Anchor::BottomLeft),
+        "bottom-right" => Some(Anchor::BottomRight),
+        "center" => Some(Anchor::Center),
+        _ => None,
+    }
+}
+
+// 根据锚点计算窗口在显示器上的位置（物理像素），水平方向留 margin，垂直方向留 top_margin
+fn anchor_position(
+    anchor: Anchor,
+    screen_pos: PhysicalPosition<i32>,
+    screen_size: PhysicalSize<u32>,
+    window_size: PhysicalSize<u32>,
+    margin: i32,
+    top_margin: i32,
+) -> PhysicalPosition<i32> {
+    let free_width = screen_size.width as i32 - window_size.width as i32;
+    let free_height = screen_size.height as i32 - window_size.height as i32;
+    let left = screen_pos.x + margin;
+    let right = screen_pos.x + free_width - margin;
+    let top = screen_pos.y + top_margin;
+    let bottom = screen_pos.y + free_height - top_margin;
+    let (x, y) = match anchor {
+        Anchor::TopLeft => (left, top),
+        Anchor::TopRight => (right, top),
+        Anchor::BottomLeft => (left, bottom),
+        Anchor::BottomRight => (right, bottom),
+        Anchor::Center => (screen_pos.x + free_width / 2, screen_pos.y + free_height / 2),
+    };
+    PhysicalPosition::new(x, y)
+}
+
+// 窗口尺寸变化后保持锚定边缘不动的新位置
+fn anchored_origin(
+    anchor: Anchor,
+    old_pos: PhysicalPosition<i32>,
+    old_size: PhysicalSize<u32>,
+    new_size: PhysicalSize<u32>,
+) -> PhysicalPosition<i32> {
+    let dw = old_size.width as i32 - new_size.width as i32;
+    let dh = old_size.height as i32 - new_size.height as i32;
+    let x = match anchor {
+        Anchor::TopLeft | Anchor::BottomLeft => old_pos.x,
+        Anchor::TopRight | Anchor::BottomRight => old_pos.x + dw,
+        Anchor::Center => old_pos.x + dw / 2,
+    };
+    let y = match anchor {
+        Anchor::TopLeft | Anchor::TopRight => old_pos.y,
+        Anchor::BottomLeft | Anchor::BottomRight => old_pos.y + dh,
+        Anchor::Center => old_pos.y + dh / 2,
+    };
+    PhysicalPosition::new(x, y)
+}
+
+// 定位窗口：优先使用保存的位置，否则按锚点定位到目标显示器
+fn position_window(window: &WebviewWindow, config: &AppConfig) {
+    // 拖动后的位置不可用时按锚点定位
+    if let Some(position) = saved_position(window, config).or_else(|| anchored_position(window, config)) {
+        move_window(window, position);
+    }
+}
+
+// 按锚点在目标显示器上的位置
+fn anchored_position(window: &WebviewWindow, config: &AppConfig) -> Option<PhysicalPosition<i32>> {
+    target_monitor(window, config).map(|monitor| {
+        let scale_factor = monitor.scale_factor();
+
+        // 按目标显示器的缩放比例换算窗口大小，跨不同 DPI 显示器时位置才准确
+        let current_scale = window.scale_factor().unwrap_or(scale_factor);
+        let window_size = window
+            .outer_size()
+            .map(|size| {
+                PhysicalSize::new(
+                    (size.width as f64 / current_scale * scale_factor) as u32,
+                    (size.height as f64 / current_scale * scale_factor) as u32,
+                )
+            })
+            .unwrap_or(PhysicalSize::new(
+                (WINDOW_WIDTH * scale_factor) as u32,
+                (WINDOW_HEIGHT * scale_factor) as u32,
+            ));
+
+        let margin = (10.0 * scale_factor) as i32;
+        let top_margin = (30.0 * scale_factor) as i32;
+
+        // 按可用区域定位，避免底部锚点被任务栏或程序坞遮挡
+        let anchor = parse_anchor(&config.anchor).unwrap_or(Anchor::TopRight);
+        let work_area = monitor.work_area();
+        anchor_position(anchor, work_area.position, work_area.size, window_size, margin, top_margin)
+    })
+}
+
+// 面板定位状态：当前锚点、拖动后的位置以及显示面板时实际使用的位置
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct PositionState {
+    anchor: String,
+    manual_position: Option<(i32, i32)>,
+    effective: (i32, i32),
+}
+
+// 拖动后的位置在显示器范围内时覆盖锚点，否则使用锚点位置
+fn build_position_state(
+    anchor: &str,
+    manual_position: Option<(i32, i32)>,
+    manual_on_screen: bool,
+    anchored: (i32, i32),
+) -> PositionState {
+    let effective = manual_position.filter(|_| manual_on_screen).unwrap_or(anchored);
+    PositionState { anchor: anchor.to_string(), manual_position, effective }
+}
+
+#[tauri::command]
+fn position_state(app: AppHandle) -> Result<PositionState, String> {
+    let window = app.get_webview_window(WINDOW_LABEL).ok_or("窗口不存在")?;
+    let config = load_config();
+    let anchored = anchored_position(&window, &config).ok_or("找不到可用的显示器")?;
+    Ok(build_position_state(
+        &config.anchor,
+        manual_position(&config, session_position()),
+        saved_position(&window, &config).is_some(),
+        (anchored.x, anchored.y),
+    ))
+}
+
+#[tauri::command]
+fn set_anchor(app: AppHandle, anchor: String) -> Result<(), String> {
+    parse_anchor(&anchor).ok_or_else(|| format!("无效的锚点: {}", anchor))?;
+
+    // 选择锚点后不再使用之前拖动保存的位置
+    let mut config = load_config();
+    config.anchor = anchor;
+    config.x = None;
+    config.y = None;
+    set_session_position(None);
+    save_config(&config)?;
+
+    if let Some(window) = app.get_webview_window(WINDOW_LABEL) {
+        if window.is_visible().unwrap_or(false) {
+            position_window(&window, &config);
+        }
+    }
+    Ok(())
+}
+
+// 通知前端面板显示状态变化，source 为触发来源：shortcut、tray、focus-loss、close、command、launch
+fn emit_visibility(app: &tauri::AppHandle, visible: bool, source: &str) {
+    let _ = app.emit("window-visibility", serde_json::json!({ "visible": visible, "source": source }));
+}
+
+fn show_window(app: &tauri::AppHandle, source: &str) {
+    if let Some(window) = app.get_webview_window(WINDOW_LABEL) {
+        let config = load_config();
+
+        apply_screen_size(&window, &config);
+        position_window(&window, &config);
+
+        let _ = window.set_skip_taskbar(should_skip_taskbar(true, config.taskbar_when_visible));
+
+        // 部分窗口管理器在显示时置顶会闪烁或抢焦点，可延迟置顶
+        let delay = always_on_top_delay(config.always_on_top_delay_ms);
+        if delay.is_some() {
+            let _ = window.set_always_on_top(false);
+        }
+        let _ = window.show();
+        let _ = window.set_focus();
+        match delay {
+            Some(delay) => {
+                let window = window.clone();
+                std::thread::spawn(move || {
+                    std::thread::sleep(delay);
+                    let _ = window.set_always_on_top(true);
+                });
+            }
+            None => {
+                let _ = window.set_always_on_top(true);
+            }
+        }
+        emit_visibility(app, true, source);
+
+        if config.refresh_on_show {
+            request_refresh(app);
+        }
+    }
+}
+
+// 距上次刷新超过最小间隔时才刷新
+fn refresh_allowed(last: Option<Instant>, now: Instant) -> bool {
+    interval_elapsed(last, now, REFRESH_MIN_INTERVAL)
+}
+
+// 通知前端刷新行情，行情由前端获取，没有自选股时前端忽略
+fn request_refresh(app: &tauri::AppHandle) {
+    let now = Instant::now();
+    let mut last = LAST_REFRESH_REQUEST.lock().unwrap_or_else(PoisonError::into_inner);
+    if !refresh_allowed(*last, now) {
+        return;
+    }
+    *last = Some(now);
+    let _ = app.emit("refresh", ());
+}
+
+#[tauri::command]
+fn set_refresh_on_show(enabled: bool) -> Result<(), String> {
+    let mut config = load_config();
+    config.refresh_on_show = enabled;
+    save_config(&config)
+}
+
+// 固定的紧急重置快捷键 Ctrl+Alt+Shift+R，不可自定义，用于窗口移出屏幕或快捷键失效时恢复
+fn emergency_shortcut() -> Shortcut {
+    Shortcut::new(Some(Modifiers::CONTROL | Modifiers::ALT | Modifiers::SHIFT), Code::KeyR)
+}
+
+// 恢复默认配置并在屏幕中央显示面板
+fn emergency_reset(app: &tauri::AppHandle) {
+    if let Err(e) = reset_config(app.clone()) {
+        log::warn!("紧急重置失败: {}", e);
+    }
+    show_window(app, "shortcut");
+    if let Some(window) = app.get_webview_window(WINDOW_LABEL) {
+        let centered = AppConfig {
+            anchor: "center".to_string(),
+            ..load_config()
+        };
+        position_window(&window, &centered);
+    }
+}
+
+fn escape_shortcut() -> Shortcut {
+    Shortcut::new(None, Code::Escape)
+}
+
+// Esc 注册状态迁移：Some(true) 需注册，Some(false) 需注销，None 保持不变
+fn escape_transition(focused: bool, enabled: bool, registered: bool) -> Option<bool> {
+    let wanted = focused && enabled;
+    if wanted == registered {
+        None
+    } else {
+        Some(wanted)
+    }
+}
+
+// 面板获得焦点时注册 Esc，失焦或隐藏时注销，避免置顶面板未聚焦时占用其他应用的 Esc
+fn sync_escape_shortcut(app: &tauri::AppHandle, focused: bool) {
+    let registered = registered_shortcut(ShortcutAction::Hide).is_some();
+    match escape_transition(focused, load_config().escape_to_hide, registered) {
+        Some(true) => match app.global_shortcut().register(escape_shortcut()) {
+            Ok(()) => set_registered_shortcut(ShortcutAction::Hide, Some(escape_shortcut())),
+            Err(e) => log::warn!("注册 Esc 快捷键失败: {}", e),
+        },
+        Some(false) => {
+            let _ = app.global_shortcut().unregister(escape_shortcut());
+            set_registered_shortcut(ShortcutAction::Hide, None);
+        }
+        None => {}
+    }
+}
+
+#[tauri::command]
+fn set_escape_to_hide(app: AppHandle, enabled: bool) -> Result<(), String> {
+    let mut config = load_config();
+    config.escape_to_hide = enabled;
+    save_config(&config)?;
+    let _ = app.emit("escape-to-hide", enabled);
+
+    let focused = app
+        .get_webview_window(WINDOW_LABEL)
+        .and_then(|window| window.is_focused().ok())
+        .unwrap_or(false);
+    sync_escape_shortcut(&app, focused);
+    Ok(())
+}
+
+// 只有面板隐藏前持有焦点时才需要归还，失焦隐藏时焦点已在其他应用
+fn should_restore_focus(enabled: bool, had_focus: bool) -> bool {
+    cfg!(target_os = "macos") && enabled && had_focus
+}
+
+// macOS 上隐藏窗口后应用仍处于激活状态，隐藏应用后系统会激活之前的应用
+// 其他平台隐藏窗口时系统会自动切换焦点，无需处理
+#[cfg(target_os = "macos")]
+fn restore_previous_focus(app: &tauri::AppHandle) {
+    let _ = app.hide();
+}
+
+#[cfg(not(target_os = "macos"))]
+fn restore_previous_focus(_app: &tauri::AppHandle) {}
+
+#[tauri::command]
+fn set_restore_prev_focus(enabled: bool) -> Result<(), String> {
+    let mut config = load_config();
+    config.restore_prev_focus = enabled;
+    save_config(&config)
+}
+
+// 隐藏时始终不在任务栏显示，显示时由 taskbar_when_visible 决定
+fn should_skip_taskbar(visible: bool, taskbar_when_visible: bool) -> bool {
+    !(visible && taskbar_when_visible)
+}
+
+#[tauri::command]
+fn set_taskbar_when_visible(app: AppHandle, enabled: bool) -> Result<(), String> {
+    let mut config = load_config();
+    config.taskbar_when_visible = enabled;
+    save_config(&config)?;
+
+    if let Some(window) = app.get_webview_window(WINDOW_LABEL) {
+        let visible = window.is_visible().unwrap_or(false);
+        let _ = window.set_skip_taskbar(should_skip_taskbar(visible, enabled));
+    }
+    Ok(())
+}
+
+// 隐藏面板，置顶时同样生效；面板已隐藏时不做任何处理
+#[tauri::command]
+fn hide_window(app: AppHandle) {
+    hide_panel(&app, "command");
+}
+
+fn hide_panel(app: &tauri::AppHandle, source: &str) {
+    let Some(window) = app.get_webview_window(WINDOW_LABEL) else {
+        return;
+    };
+    if !window.is_visible().unwrap_or(false) {
+        return;
+    }
+    let had_focus = window.is_focused().unwrap_or(false);
+    let _ = window.hide();
+    let _ = window.set_skip_taskbar(should_skip_taskbar(false, load_config().taskbar_when_visible));
+    if should_restore_focus(load_config().restore_prev_focus, had_focus) {
+        restore_previous_focus(app);
+    }
+    sync_escape_shortcut(app, false);
+    emit_visibility(app, false, source);
+}
+
+// 面板切换记录
+#[derive(Debug, Clone, Serialize)]
+struct ToggleEvent {
+    // Unix 时间戳（毫秒）
+    timestamp: u64,
+    // 切换后是否可见
+    visible: bool,
+}
+
+// 追加记录，超出上限时丢弃最旧的
+fn push_toggle_event(history: &mut VecDeque<ToggleEvent>, event: ToggleEvent, cap: usize) {
+    history.push_back(event);
+    while history.len() > cap {
+        history.pop_front();
+    }
+}
+
+// 取最近 limit 条记录，最新的在前
+fn recent_toggle_events(history: &VecDeque<ToggleEvent>, limit: usize) -> Vec<ToggleEvent> {
+    history.iter().rev().take(limit).cloned().collect()
+}
+
+fn record_toggle(visible: bool) {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+    if let Ok(mut history) = TOGGLE_HISTORY.lock() {
+        push_toggle_event(&mut history, ToggleEvent { timestamp, visible }, TOGGLE_HISTORY_CAP);
+    }
+}
+
+#[tauri::command]
+fn toggle_history(limit: usize) -> Vec<ToggleEvent> {
+    TOGGLE_HISTORY
+        .lock()
+        .map(|history| recent_toggle_events(&history, limit))
+        .unwrap_or_default()
+}
+
+fn toggle_window(app: &tauri::AppHandle, source: &str) {
+    if let Some(window) = app.get_webview_window(WINDOW_LABEL) {
+        if window.is_visible().unwrap_or(false) {
+            hide_panel(app, source);
+            record_toggle(false);
+        } else {
+            show_window(app, source);
+            record_toggle(true);
+        }
+    }
+}
+
+fn create_window(app: &tauri::AppHandle, config: &AppConfig) -> tauri::Result<()> {
+    let window = WebviewWindowBuilder::new(app, WINDOW_LABEL, WebviewUrl::default())
+        .title("Millionaire")
+        .inner_size(config.window_width, config.window_height)
+        .min_inner_size(WINDOW_WIDTH, WINDOW_HEIGHT)
+        .resizable(true)
+        .decorations(false)
+        .transparent(true)
+        .always_on_top(true)
+        .visible(false)
+        .skip_taskbar(true)
+        .initialization_script(opacity_script(current_opacity(config)))
+        .build()?;
+
+    // 设置失败时只记录日志，不影响后面注册窗口事件
+    if config.click_through {
+        match window.set_ignore_cursor_events(true) {
+            Ok(()) => CLICK_THROUGH.store(true, Ordering::SeqCst),
+            Err(e) => log::warn!("开启鼠标穿透失败: {}", e),
+        }
+    }
+
+    let window_clone = window.clone();
+    window.on_window_event(move |event| {
+        match event {
+            tauri::WindowEvent::Focused(focused) => {
+                sync_escape_shortcut(window_clone.app_handle(), *focused);
+                // 失焦时立即写入尚未保存的位置等修改
+                if !focused {
+                    flush_pending_config();
+                }
+                // 置顶模式、速览期间、仅托盘模式以及鼠标穿透时不自动隐藏
+                // 鼠标穿透时点击会落到下方窗口并使面板失焦，此时隐藏会让穿透失去意义
+                if !focused
+                    && AUTO_HIDE.load(Ordering::SeqCst)
+                    && !CLICK_THROUGH.load(Ordering::SeqCst)
+                    && !PINNED.load(Ordering::SeqCst)
+                    && !PEEKING.load(Ordering::SeqCst)
+                {
+                    hide_panel(window_clone.app_handle(), "focus-loss");
+                }
+            }
+            // 不随窗口关闭退出时只隐藏窗口，之后仍可通过托盘或快捷键打开
+            tauri::WindowEvent::CloseRequested { api, .. } if !load_config().exit_on_last_window_close => {
+                api.prevent_close();
+                hide_panel(window_clone.app_handle(), "close");
+            }
+            tauri::WindowEvent::Resized(size) => {
+                // 临时布局产生的尺寸变化不保存
+                if transient_layout_active() {
+                    return;
+                }
+                // 交给后台线程合并写入，停止缩放一段时间后才写入
+                if let Ok(mut last) = LAST_USER_MOVE.lock() {
+                    *last = Some(Instant::now());
+                }
+                let scale = window_clone.scale_factor().unwrap_or(1.0);
+                let mut config = load_config();
+                config.window_width = size.width as f64 / scale;
+                config.window_height = size.height as f64 / scale;
+                queue_config_write(config);
+            }
+            tauri::WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                // 临时布局的尺寸由各自逻辑维护
+                let config = load_config();
+                if !config.track_dpi || transient_layout_active() {
+                    return;
+                }
+                let _ = window_clone.set_size(scaled_physical_size(
+                    config.window_width,
+                    config.window_height,
+                    *scale_factor,
+                ));
+            }
+            tauri::WindowEvent::Moved(position) => {
+                // 只记录用户拖动产生的位置（窗口可见且非程序主动移动）
+                let mut config = load_config();
+                let Some(persist) = persist_move(
+                    take_programmatic_move(*position),
+                    transient_layout_active(),
+                    window_clone.is_visible().unwrap_or(false),
+                    config.persist_manual_position,
+                ) else {
+                    return;
+                };
+                if let Ok(mut last) = LAST_USER_MOVE.lock() {
+                    *last = Some(Instant::now());
+                }
+                if persist {
+                    config.x = Some(position.x);
+                    config.y = Some(position.y);
+                    queue_config_write(config);
+                } else {
+                    set_session_position(Some((position.x, position.y)));
+                }
+            }
+            _ => {}
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg_attr(mobile, tauri::mobile_entry_point)]
+pub fn run() {
+    tauri::Builder::default()
+        // 需最先注册：再次启动时由已运行的实例显示面板，新进程直接退出
+        .plugin(tauri_plugin_single_instance::init(|app, _args, _cwd| {
+            show_window(app, "launch");
+        }))
+        // 日志写入标准输出和应用日志目录，各处的 log::warn! 依赖此插件输出
+        .plugin(
+            tauri_plugin_log::Builder::new()
+                .level(log::LevelFilter::Info)
+                .build(),
+        )
+        .plugin(tauri_plugin_http::init())
+        .plugin(tauri_plugin_store::Builder::default().build())
+        .plugin(tauri_plugin_autostart::init(
+            MacosLauncher::LaunchAgent,
+            Some(vec![AUTOSTART_ARG]),
+        ))
+        .invoke_handler(tauri::generate_handler![
+            set_pinned,
+            get_pinned,
+            update_shortcut,
+            get_shortcut,
+            save_window_size,
+            launched_at_login,
+            update_settings_shortcut,
+            set_max_modifiers,
+            set_show_on_active_monitor,
+            set_max_pin_duration,
+            config_size_bytes,
+            set_time_format,
+            update_tray_tooltip,
+            set_hover_expand,
+            hover_changed,
+            set_locale,
+            toggle_dot_mode,
+            export_shortcuts,
+            import_shortcuts,
+            set_persist_manual_position,
+            repair_config,
+            position_state,
+            set_api_key,
+            get_api_key,
+            clear_api_key,
+            rebuild_tray_menu,
+            set_escape_to_hide,
+            previous_session_crashed,
+            apply_aspect_preset,
+            set_drag_region,
+            set_restore_prev_focus,
+            window_rect_physical,
+            set_min_write_interval,
+            set_refresh_on_show,
+            tray_available,
+            set_taskbar_when_visible,
+            update_refresh_shortcut,
+            config_defaults,
+            save_tour,
+            next_tour_step,
+            startup_report,
+            show_detail_bubble,
+            hide_detail_bubble,
+            set_always_on_top_delay,
+            toggle_history,
+            allowed_modifier_presets,
+            force_redraw,
+            update_peek_shortcut,
+            update_pin_shortcut,
+            get_config,
+            reset_config,
+            acknowledge_crash,
+            set_home,
+            go_home,
+            set_anchor,
+            set_opacity,
+            set_night_opacity,
+            sync_night_opacity,
+            supported_keys,
+            set_small_screen_size,
+            set_interaction_model,
+            config_path,
+            key_allows_no_modifier,
+            set_click_through,
+            set_exit_on_last_window_close,
+            export_config,
+            import_config,
+            config_load_time_ms,
+            reload_config_from_disk,
+            set_autostart,
+            set_resize_border,
+            set_track_dpi,
+            hide_window,
+        ])
+        .plugin(
+            tauri_plugin_global_shortcut::Builder::new()
+                .with_handler(|_app, shortcut, event| {
+                    // 按触发的快捷键分发，未登记的快捷键不做处理
+                    let Some(action) = shortcut_action(shortcut) else {
+                        return;
+                    };
+                    if let Some(dispatcher) = SHORTCUT_EVENTS.lock().ok().and_then(|slot| slot.clone()) {
+                        let _ = dispatcher.send((action, event.state()));
+                    }
+                })
+                .build(),
+        )
+        .setup(|app| {
+            // 记录是否为开机自启
+            let args: Vec<String> = std::env::args().collect();
+            LAUNCHED_AT_LOGIN.store(is_autostart_launch(&args), Ordering::SeqCst);
+
+            // 初始化配置文件路径
+            if let Ok(app_data_dir) = app.path().app_data_dir() {
+                let config_path = app_data_dir.join(CONFIG_FILE);
+                if let Ok(mut path) = CONFIG_PATH.lock() {
+                    *path = Some(config_path);
+                }
+            }
+
+            // 检查上次是否崩溃并开始写入心跳
+            start_heartbeat();
+
+            // 启动配置合并写入线程
+            start_config_flusher();
+
+            // 同步开机自启状态
+            reconcile_autostart(app.handle());
+
+            // 加载配置
+            let config = load_config();
+
+            // 恢复置顶状态，需在窗口事件回调注册前完成
+            PINNED.store(config.pinned, Ordering::SeqCst);
+            start_auto_unpin_timer(app.handle().clone());
+            schedule_auto_unpin(auto_unpin_delay(config.pinned, config.max_pin_duration_secs));
+
+            // 快捷键事件分发线程，需在注册快捷键前启动
+            start_shortcut_dispatcher(app.handle().clone());
+
+            // 紧急重置快捷键独立注册，用户快捷键注册失败时仍可使用
+            match app.global_shortcut().register(emergency_shortcut()) {
+                Ok(()) => set_registered_shortcut(ShortcutAction::EmergencyReset, Some(emergency_shortcut())),
+                Err(e) => log::warn!("注册紧急重置快捷键失败: {}", e),
+            }
+
+            // 各子系统独立初始化，单个失败不影响其他部分
+            let window_result = create_window(app.handle(), &config).map_err(|e| e.to_string());
+            let tray_result = setup_tray(app.handle(), &config);
+            // 仅托盘模式下不注册用户快捷键
+            let effects = interaction_effects(parse_interaction_model(&config.interaction_model));
+            AUTO_HIDE.store(effects.auto_hide, Ordering::SeqCst);
+            let shortcut_result = if effects.global_shortcuts {
+                register_user_shortcuts(app.handle(), &config)
+            } else {
+                Ok(())
+            };
+            let report = build_startup_report(window_result, tray_result, shortcut_result);
+            for error in &report.errors {
+                log::warn!("启动初始化失败: {}", error);
+            }
+            TRAY_AVAILABLE.store(report.tray, Ordering::SeqCst);
+            if let Ok(mut current) = STARTUP_REPORT.lock() {
+                *current = Some(report);
+            }
+
+            // 恢复上次的布局模式，需在显示前完成
+            if parse_layout_mode(&config.last_layout_mode) == LayoutMode::Dot {
+                if let Some(window) = app.get_webview_window(WINDOW_LABEL) {
+                    apply_dot_mode(&window, &config, true);
+                }
+            }
+
+            // 初始化快捷键配置到内存
+            *lock_current_shortcut() = Some((config.shortcut_modifiers.clone(), config.shortcut_key.clone()));
+
+            Ok(())
+        })
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|_app, event| match event {
+            tauri::RunEvent::ExitRequested { code, api, .. }
+                if prevent_exit(code, load_config().exit_on_last_window_close) =>
+            {
+                api.prevent_exit();
+            }
+            // 正常退出时写入待写配置，并标记心跳，下次启动不再判定为崩溃
+            tauri::RunEvent::Exit => {
+                flush_pending_config();
+                stop_heartbeat();
+            }
+            _ => {}
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    #[test]
+    fn parse_key_navigation_aliases() {
+        let cases = [
+            ("UP", Code::ArrowUp),
+            ("ARROWUP", Code::ArrowUp),
+            ("DOWN", Code::ArrowDown),
+            ("ARROWDOWN", Code::ArrowDown),
+            ("LEFT", Code::ArrowLeft),
+            ("ARROWLEFT", Code::ArrowLeft),
+            ("RIGHT", Code::ArrowRight),
+            ("ARROWRIGHT", Code::ArrowRight),
+            ("BACKSPACE", Code::Backspace),
+            ("DELETE", Code::Delete),
+            ("DEL", Code::Delete),
+            ("HOME", Code::Home),
+            ("END", Code::End),
+            ("PAGEUP", Code::PageUp),
+            ("PGUP", Code::PageUp),
+            ("PAGEDOWN", Code::PageDown),
+            ("PGDN", Code::PageDown),
+        ];
+        for (alias, code) in cases {
+            assert_eq!(parse_key(alias), Some(code), "{}", alias);
+        }
+        // 忽略大小写
+        assert_eq!(parse_key("pgdn"), Some(Code::PageDown));
+    }
+
+    #[test]
+    fn parse_key_numpad_aliases() {
+        let cases = [
+            ("NUMPAD0", Code::Numpad0),
+            ("NUMPAD1", Code::Numpad1),
+            ("NUMPAD2", Code::Numpad2),
+            ("NUMPAD3", Code::Numpad3),
+            ("NUMPAD4", Code::Numpad4),
+            ("NUMPAD5", Code::Numpad5),
+            ("NUMPAD6", Code::Numpad6),
+            ("NUMPAD7", Code::Numpad7),
+            ("NUMPAD8", Code::Numpad8),
+            ("NUMPAD9", Code::Numpad9),
+            ("NUMPADADD", Code::NumpadAdd),
+            ("NUMPADSUBTRACT", Code::NumpadSubtract),
+            ("NUMPADMULTIPLY", Code::NumpadMultiply),
+            ("NUMPADDIVIDE", Code::NumpadDivide),
+            ("NUMPADENTER", Code::NumpadEnter),
+        ];
+        for (alias, code) in cases {
+            assert_eq!(parse_key(alias), Some(code), "{}", alias);
+        }
+        assert_eq!(parse_key("NUMPADX"), None);
+    }
+
+    // 每个测试使用独立的临时目录，避免并行运行时互相影响
+    fn temp_config_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("millionaire-test-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir.join(CONFIG_FILE)
+    }
+
+    // 写入配置文件再按启动时的方式读回
+    fn round_trip(name: &str, config: &AppConfig) -> AppConfig {
+        let path = temp_config_path(name);
+        write_config_file(&path, config).unwrap();
+        read_config_file(&path)
+    }
+
+    #[test]
+    fn corrupt_config_is_backed_up_with_original_bytes() {
+        let path = temp_config_path("corrupt");
+        let garbage = b"{ \"window_width\": 300, oops";
+        fs::write(&path, garbage).unwrap();
+
+        let config = read_config_file(&path);
+
+        assert_eq!(config.window_width, AppConfig::default().window_width);
+        assert!(!path.exists());
+        assert_eq!(fs::read(path.with_extension("json.bak")).unwrap(), garbage);
+    }
+
+    #[test]
+    fn missing_config_is_not_backed_up() {
+        let path = temp_config_path("missing");
+
+        read_config_file(&path);
+
+        assert!(!path.with_extension("json.bak").exists());
+    }
+
+    #[test]
+    fn v0_config_migrates_to_current_version() {
+        let path = temp_config_path("v0");
+        fs::write(&path, r#"{ "shortcut_modifiers": ["Ctrl"], "shortcut_key": "K" }"#).unwrap();
+
+        let config = read_config_file(&path);
+
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.shortcut_modifiers, vec!["Ctrl".to_string()]);
+        assert_eq!(config.shortcut_key, "K");
+        // 缺失的字段使用默认值
+        assert_eq!(config.window_width, WINDOW_WIDTH);
+        assert_eq!(config.anchor, AppConfig::default().anchor);
+    }
+
+    #[test]
+    fn shortcut_display_round_trips() {
+        let names = ["Alt", "Ctrl", "Shift", "Meta"];
+        for mask in 0..(1 << names.len()) {
+            let modifiers: Vec<String> = names
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| mask & (1 << i) != 0)
+                .map(|(_, name)| name.to_string())
+                .collect();
+            for (key, _) in KEYS {
+                let display = format_shortcut_display(&modifiers, key);
+                assert_eq!(
+                    parse_shortcut_display(&display),
+                    Some((modifiers.clone(), key.to_string())),
+                    "{}",
+                    display
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn supported_keys_all_parse() {
+        let keys = supported_keys();
+        assert!(!keys.is_empty());
+        for key in keys {
+            assert!(parse_key(&key).is_some(), "{}", key);
+        }
+    }
+
+    #[test]
+    fn zero_window_size_is_bumped_to_minimum() {
+        let path = temp_config_path("zero-size");
+        fs::write(&path, r#"{ "window_width": 0.0, "window_height": 99999.0 }"#).unwrap();
+
+        let config = read_config_file(&path);
+
+        assert_eq!(config.window_width, WINDOW_WIDTH);
+        assert_eq!(config.window_height, MAX_WINDOW_SIZE);
+    }
+
+    #[test]
+    fn repair_keeps_valid_modifier_presets() {
+        let mut config = AppConfig {
+            shortcut_modifiers: vec!["Cmd".to_string(), "Option".to_string()],
+            settings_shortcut_modifiers: vec!["Ctrl".to_string(), "Alt".to_string()],
+            settings_shortcut_key: "S".to_string(),
+            ..AppConfig::default()
+        };
+
+        assert!(repair_config_fields(&mut config).is_empty());
+        assert_eq!(config.shortcut_modifiers, vec!["Cmd".to_string(), "Option".to_string()]);
+        assert_eq!(config.settings_shortcut_modifiers, vec!["Ctrl".to_string(), "Alt".to_string()]);
+    }
+
+    #[test]
+    fn repair_drops_unknown_modifiers_and_keys() {
+        let mut config = AppConfig {
+            peek_shortcut_modifiers: vec!["Hyper".to_string(), "Shift".to_string()],
+            peek_shortcut_key: "P".to_string(),
+            pin_shortcut_key: "NotAKey".to_string(),
+            ..AppConfig::default()
+        };
+
+        assert_eq!(repair_config_fields(&mut config).len(), 2);
+        assert_eq!(config.peek_shortcut_modifiers, vec!["Shift".to_string()]);
+        assert!(config.pin_shortcut_key.is_empty());
+    }
+
+    #[test]
+    fn small_screen_only_for_netbook_sizes() {
+        let config = AppConfig::default();
+        // 1920×1080 缩放 150% 的可用区域
+        assert_eq!(small_screen_size(1280.0, 690.0, &config), None);
+        assert_eq!(
+            small_screen_size(1024.0, 560.0, &config),
+            Some((config.small_screen_width, config.small_screen_height))
+        );
+    }
+
+    #[test]
+    fn autostart_flag_detected_in_launch_args() {
+        let args = |list: &[&str]| list.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+        assert!(is_autostart_launch(&args(&["millionaire", "--autostart"])));
+        assert!(is_autostart_launch(&args(&["millionaire", "--verbose", "--autostart"])));
+        assert!(!is_autostart_launch(&args(&["millionaire"])));
+        assert!(!is_autostart_launch(&args(&[])));
+        // 程序路径本身不算启动参数
+        assert!(!is_autostart_launch(&args(&["--autostart"])));
+        assert!(!is_autostart_launch(&args(&["millionaire", "--autostart=false"])));
+    }
+
+    #[test]
+    fn settings_combo_dispatches_to_navigate() {
+        let settings = Shortcut::new(Some(Modifiers::CONTROL | Modifiers::SHIFT), Code::KeyS);
+        let toggle = Shortcut::new(Some(Modifiers::ALT), Code::KeyM);
+        let registry = [(toggle, ShortcutAction::Toggle), (settings, ShortcutAction::Settings)];
+
+        assert_eq!(find_action(&registry, &settings), Some(ShortcutAction::Settings));
+        assert_eq!(find_action(&registry, &toggle), Some(ShortcutAction::Toggle));
+        assert_eq!(find_action(&registry, &Shortcut::new(None, Code::F5)), None);
+        assert_eq!(navigate_payload("settings"), serde_json::json!({ "route": "settings" }));
+    }
+
+    #[test]
+    fn modifier_limit_at_under_and_over() {
+        let mods = |list: &[&str]| list.iter().map(|m| m.to_string()).collect::<Vec<_>>();
+        assert!(check_modifier_limit(&mods(&["Ctrl"]), Some(2)).is_ok());
+        assert!(check_modifier_limit(&mods(&["Ctrl", "Alt"]), Some(2)).is_ok());
+        assert!(check_modifier_limit(&mods(&["Ctrl", "Alt", "Shift"]), Some(2)).is_err());
+        assert!(check_modifier_limit(&mods(&["Ctrl", "Alt", "Shift", "Meta"]), None).is_ok());
+        // 同一修饰键的别名只计一次
+        assert!(check_modifier_limit(&mods(&["Ctrl", "Control"]), Some(1)).is_ok());
+    }
+
+    #[test]
+    fn monitor_selection_follows_cursor() {
+        let bounds = [
+            (PhysicalPosition::new(0, 0), PhysicalSize::new(1920, 1080)),
+            (PhysicalPosition::new(1920, 0), PhysicalSize::new(2560, 1440)),
+        ];
+        let on_first = Some(PhysicalPosition::new(100.0, 100.0));
+        let on_second = Some(PhysicalPosition::new(2000.0, 500.0));
+        let off_screen = Some(PhysicalPosition::new(-50.0, 100.0));
+
+        // 使用鼠标所在的显示器
+        assert_eq!(pick_monitor_index(&bounds, &[on_first]), Some(0));
+        assert_eq!(pick_monitor_index(&bounds, &[on_second]), Some(1));
+        // 鼠标位置不可用或不在任何显示器上时回退到主显示器
+        assert_eq!(pick_monitor_index(&bounds, &[None]), None);
+        assert_eq!(pick_monitor_index(&bounds, &[off_screen]), None);
+        // 右边界属于相邻的显示器
+        assert_eq!(pick_monitor_index(&bounds, &[Some(PhysicalPosition::new(1920.0, 0.0))]), Some(1));
+    }
+
+    #[test]
+    fn reset_timer_fires_and_resets() {
+        let (fired_tx, fired) = mpsc::channel();
+        let timer = spawn_reset_timer(move || {
+            let _ = fired_tx.send(());
+        });
+
+        // 到期后触发
+        timer.send(Some(Duration::from_millis(20))).unwrap();
+        assert!(fired.recv_timeout(Duration::from_secs(2)).is_ok());
+
+        // 计时期间重新计时，以最后一次的时长为准
+        timer.send(Some(Duration::from_millis(100))).unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+        timer.send(Some(Duration::from_millis(400))).unwrap();
+        assert!(fired.recv_timeout(Duration::from_millis(200)).is_err());
+        assert!(fired.recv_timeout(Duration::from_secs(2)).is_ok());
+
+        // 取消后不再触发
+        timer.send(Some(Duration::from_millis(50))).unwrap();
+        timer.send(None).unwrap();
+        assert!(fired.recv_timeout(Duration::from_millis(300)).is_err());
+    }
+
+    #[test]
+    fn auto_unpin_only_when_pinned_with_limit() {
+        assert_eq!(auto_unpin_delay(true, 60), Some(Duration::from_secs(60)));
+        assert_eq!(auto_unpin_delay(true, 0), None);
+        assert_eq!(auto_unpin_delay(false, 60), None);
+    }
+
+    #[test]
+    fn serialized_size_matches_pretty_json() {
+        let config = AppConfig {
+            shortcut_modifiers: vec!["Ctrl".to_string(), "Shift".to_string()],
+            shortcut_key: "K".to_string(),
+            locale: "de-DE".to_string(),
+            ..AppConfig::default()
+        };
+        let content = serde_json::to_string_pretty(&config).unwrap();
+
+        assert_eq!(serialized_size(&config), content.len());
+        // 按字节计算，而不是字符数
+        let with_tour = AppConfig {
+            tours: HashMap::from([("演示".to_string(), Vec::new())]),
+            ..config
+        };
+        let content = serde_json::to_string_pretty(&with_tour).unwrap();
+        assert!(content.len() > content.chars().count());
+        assert_eq!(serialized_size(&with_tour), content.len());
+    }
+
+    #[test]
+    fn format_time_in_both_formats() {
+        assert_eq!(format_time(14, 5, 9, "24h"), "14:05:09");
+        assert_eq!(format_time(14, 5, 9, "12h"), "02:05:09 PM");
+        assert_eq!(format_time(0, 30, 0, "24h"), "00:30:00");
+        assert_eq!(format_time(0, 30, 0, "12h"), "12:30:00 AM");
+        assert_eq!(format_time(12, 0, 0, "12h"), "12:00:00 PM");
+    }
+
+    #[test]
+    fn hover_resize_on_enter_and_leave() {
+        let config = AppConfig {
+            expand_on_hover: true,
+            ..AppConfig::default()
+        };
+        let expanded_size = (config.hover_expand_width, config.hover_expand_height);
+        let base_size = (config.window_width, config.window_height);
+
+        assert_eq!(hover_target_size(&config, true), Some(expanded_size));
+        assert_eq!(hover_target_size(&config, false), Some(base_size));
+        assert_eq!(hover_resize(&config, true, false, false), Some(expanded_size));
+        assert_eq!(hover_resize(&config, false, false, true), Some(base_size));
+        // 状态未变化时不调整
+        assert_eq!(hover_resize(&config, true, false, true), None);
+        assert_eq!(hover_resize(&config, false, false, false), None);
+        // 防抖期间有更新的悬停事件时放弃本次
+        assert_eq!(hover_resize(&config, true, true, false), None);
+        // 未启用时不调整
+        assert_eq!(hover_resize(&AppConfig::default(), true, false, false), None);
+    }
+
+    #[test]
+    fn format_number_per_locale() {
+        assert_eq!(format_number(1234.56, "en-US"), "1,234.56");
+        assert_eq!(format_number(1234.56, "de-DE"), "1.234,56");
+        assert_eq!(format_number(1234.56, "zh-CN"), "1,234.56");
+        assert_eq!(format_number(1234567.891, "en-US"), "1,234,567.89");
+        assert_eq!(format_number(-1234.5, "de-DE"), "-1.234,50");
+        assert_eq!(format_number(999.999, "en-US"), "1,000.00");
+        assert_eq!(format_number(12.3, "en-US"), "12.30");
+        assert_eq!(format_number(-0.001, "en-US"), "0.00");
+    }
+
+    #[test]
+    fn dot_toggle_restores_saved_geometry() {
+        let config = AppConfig {
+            window_width: 320.0,
+            window_height: 480.0,
+            ..AppConfig::default()
+        };
+
+        assert_eq!(dot_mode_size(&config, true), (DOT_SIZE, DOT_SIZE));
+        assert_eq!(dot_mode_size(&config, false), (320.0, 480.0));
+    }
+
+    #[test]
+    fn portable_shortcut_parsing() {
+        assert_eq!(
+            parse_portable_shortcut("Alt+Shift+M"),
+            Some((vec!["Alt".to_string(), "Shift".to_string()], "M".to_string()))
+        );
+        assert_eq!(parse_portable_shortcut(" Ctrl + F5 "), Some((vec!["Ctrl".to_string()], "F5".to_string())));
+        assert_eq!(parse_portable_shortcut(""), Some((Vec::new(), String::new())));
+        assert_eq!(parse_portable_shortcut("Hyper+M"), None);
+        assert_eq!(parse_portable_shortcut("Alt+"), None);
+    }
+
+    #[test]
+    fn shortcut_bindings_round_trip() {
+        let config = AppConfig {
+            shortcut_modifiers: vec!["Ctrl".to_string(), "Alt".to_string()],
+            shortcut_key: "K".to_string(),
+            pin_shortcut_modifiers: vec!["Shift".to_string()],
+            pin_shortcut_key: "F9".to_string(),
+            ..AppConfig::default()
+        };
+        let json = serde_json::to_string(&shortcut_bindings(&config)).unwrap();
+
+        assert_eq!(
+            parse_shortcut_bindings(&json).unwrap(),
+            vec![
+                ("toggle".to_string(), config.shortcut_modifiers.clone(), "K".to_string()),
+                ("settings".to_string(), Vec::new(), String::new()),
+                ("peek".to_string(), Vec::new(), String::new()),
+                ("pin".to_string(), config.pin_shortcut_modifiers.clone(), "F9".to_string()),
+                ("refresh".to_string(), Vec::new(), String::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_shortcut_binding_rejects_whole_import() {
+        // 任何一条无效时整体返回错误，有效的条目也不会被应用
+        let invalid_key = r#"[{ "action": "toggle", "shortcut": "Alt+K" }, { "action": "pin", "shortcut": "Ctrl+NotAKey" }]"#;
+        let unknown_action = r#"[{ "action": "toggle", "shortcut": "Alt+K" }, { "action": "zoom", "shortcut": "Alt+Z" }]"#;
+        let empty_toggle = r#"[{ "action": "toggle", "shortcut": "" }]"#;
+
+        assert!(parse_shortcut_bindings(invalid_key).is_err());
+        assert!(parse_shortcut_bindings(unknown_action).is_err());
+        assert!(parse_shortcut_bindings(empty_toggle).is_err());
+        assert!(parse_shortcut_bindings("not json").is_err());
+        // 兼容界面上显示的符号形式
+        assert_eq!(
+            parse_shortcut_bindings(r#"[{ "action": "toggle", "shortcut": "⌥⇧M" }]"#).unwrap(),
+            vec![("toggle".to_string(), vec!["Alt".to_string(), "Shift".to_string()], "M".to_string())]
+        );
+    }
+
+    // 内存中的 SecretStore，记录保存次数
+    #[derive(Default)]
+    struct MemoryStore {
+        entries: std::cell::RefCell<HashMap<String, serde_json::Value>>,
+        saves: std::cell::Cell<usize>,
+    }
+
+    impl SecretStore for MemoryStore {
+        fn get(&self, key: &str) -> Option<serde_json::Value> {
+            self.entries.borrow().get(key).cloned()
+        }
+
+        fn set(&self, key: &str, value: serde_json::Value) {
+            self.entries.borrow_mut().insert(key.to_string(), value);
+        }
+
+        fn delete(&self, key: &str) {
+            self.entries.borrow_mut().remove(key);
+        }
+
+        fn save(&self) -> Result<(), String> {
+            self.saves.set(self.saves.get() + 1);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn api_key_set_get_clear() {
+        let store = MemoryStore::default();
+        assert_eq!(read_api_key(&store), None);
+
+        store_api_key(&store, "secret".to_string()).unwrap();
+        assert_eq!(read_api_key(&store), Some("secret".to_string()));
+        assert_eq!(store.saves.get(), 1);
+
+        store_api_key(&store, "rotated".to_string()).unwrap();
+        assert_eq!(read_api_key(&store), Some("rotated".to_string()));
+
+        remove_api_key(&store).unwrap();
+        assert_eq!(read_api_key(&store), None);
+        assert_eq!(store.saves.get(), 3);
+    }
+
+    #[test]
+    fn tray_label_follows_updated_shortcut() {
+        assert_eq!(tray_show_label(&["Alt".to_string()], "M"), "显示面板 (⌥M)");
+        let updated = AppConfig {
+            shortcut_modifiers: vec!["Ctrl".to_string(), "Shift".to_string()],
+            shortcut_key: "K".to_string(),
+            ..AppConfig::default()
+        };
+        assert_eq!(
+            tray_show_label(&updated.shortcut_modifiers, &updated.shortcut_key),
+            "显示面板 (⌃⇧K)"
+        );
+    }
+
+    #[test]
+    fn escape_registered_on_show_and_unregistered_on_hide() {
+        // 显示并获得焦点时注册
+        assert_eq!(escape_transition(true, true, false), Some(true));
+        // 已注册时不重复注册
+        assert_eq!(escape_transition(true, true, true), None);
+        // 失焦或隐藏时注销
+        assert_eq!(escape_transition(false, true, true), Some(false));
+        assert_eq!(escape_transition(false, true, false), None);
+        // 关闭 escape_to_hide 后注销且不再注册
+        assert_eq!(escape_transition(true, false, true), Some(false));
+        assert_eq!(escape_transition(true, false, false), None);
+    }
+
+    #[test]
+    fn recent_unclean_heartbeat_is_a_crash() {
+        let recent = Some(Duration::from_secs(45));
+        assert!(is_probable_crash(recent, false));
+        // 正常退出
+        assert!(!is_probable_crash(recent, true));
+        // 过期的心跳不算崩溃
+        assert!(!is_probable_crash(Some(HEARTBEAT_MAX_AGE + Duration::from_secs(1)), false));
+        // 首次运行没有心跳
+        assert!(!is_probable_crash(None, false));
+    }
+
+    #[test]
+    fn aspect_presets_keep_width() {
+        let close = |a: (f64, f64), b: (f64, f64)| (a.0 - b.0).abs() < 1e-9 && (a.1 - b.1).abs() < 1e-9;
+        assert!(close(aspect_preset_size("square", 560.0).unwrap(), (560.0, 560.0)));
+        assert!(close(aspect_preset_size("16:9", 640.0).unwrap(), (640.0, 360.0)));
+        assert!(close(aspect_preset_size("4:3", 480.0).unwrap(), (480.0, 360.0)));
+        assert!(close(aspect_preset_size("ultrawide", 840.0).unwrap(), (840.0, 360.0)));
+        // 高度低于最小值时按比例放大宽度
+        assert!(close(aspect_preset_size("16:9", 280.0).unwrap(), (WINDOW_HEIGHT * 16.0 / 9.0, WINDOW_HEIGHT)));
+        assert_eq!(aspect_preset_size("3:2", 600.0), None);
+    }
+
+    #[test]
+    fn drag_region_validation_and_round_trip() {
+        assert!(is_valid_drag_region("whole"));
+        assert!(is_valid_drag_region("header"));
+        assert!(!is_valid_drag_region("footer"));
+        assert!(!is_valid_drag_region("Whole"));
+
+        let config = AppConfig {
+            drag_region: "whole".to_string(),
+            ..AppConfig::default()
+        };
+        let restored: AppConfig = serde_json::from_str(&serde_json::to_string(&config).unwrap()).unwrap();
+        assert_eq!(restored.drag_region, "whole");
+
+        // 无效值修复为默认值
+        let mut invalid = AppConfig {
+            drag_region: "footer".to_string(),
+            ..AppConfig::default()
+        };
+        repair_config_fields(&mut invalid);
+        assert_eq!(invalid.drag_region, AppConfig::default().drag_region);
+    }
+
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn restore_focus_only_when_panel_had_focus() {
+        assert!(should_restore_focus(true, true));
+        assert!(!should_restore_focus(true, false));
+        assert!(!should_restore_focus(false, true));
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    #[test]
+    fn restore_focus_is_a_no_op_off_macos() {
+        for (enabled, had_focus) in [(true, true), (true, false), (false, true), (false, false)] {
+            assert!(!should_restore_focus(enabled, had_focus));
+        }
+    }
+
+    #[test]
+    fn physical_rect_maps_position_and_size() {
+        let rect = physical_rect(PhysicalPosition::new(-1200, 40), PhysicalSize::new(560, 600), 2.0);
+        assert_eq!((rect.x, rect.y, rect.width, rect.height), (-1200, 40, 560, 600));
+        assert_eq!(rect.scale_factor, 2.0);
+        assert_eq!(
+            serde_json::to_value(&rect).unwrap(),
+            serde_json::json!({ "x": -1200, "y": 40, "width": 560, "height": 600, "scale_factor": 2.0 })
+        );
+    }
+
+    #[test]
+    fn writes_coalesce_until_interval_elapses() {
+        let interval = Duration::from_millis(1000);
+        let last_write = Instant::now();
+        // 从未写入时立即写入
+        assert!(interval_elapsed(None, last_write, interval));
+        // 间隔内的修改合并到下一次写入
+        assert!(!interval_elapsed(Some(last_write), last_write + Duration::from_millis(200), interval));
+        assert!(interval_elapsed(Some(last_write), last_write + interval, interval));
+        assert!(interval_elapsed(Some(last_write), last_write + Duration::from_secs(5), interval));
+    }
+
+    #[test]
+    fn exit_flush_writes_pending_config() {
+        let path = temp_config_path("exit-flush");
+        let pending = Mutex::new(Some(AppConfig {
+            window_width: 640.0,
+            ..AppConfig::default()
+        }));
+
+        flush_pending(&pending, &path);
+
+        assert!(pending.lock().unwrap().is_none());
+        assert_eq!(read_config_file(&path).window_width, 640.0);
+
+        // 没有待写配置时不写入
+        fs::remove_file(&path).unwrap();
+        flush_pending(&pending, &path);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn tray_dependent_settings_need_a_tray() {
+        // 托盘创建失败时记录为不可用
+        let report = build_startup_report(Ok(()), Err("托盘图标不存在".to_string()), Ok(()));
+        assert!(!report.tray);
+        assert!(build_startup_report(Ok(()), Ok(()), Ok(())).tray);
+
+        // 没有托盘时只允许快捷键模式且不开启鼠标穿透
+        assert!(check_tray_dependency(false, InteractionModel::Hotkey, false).is_ok());
+        assert!(check_tray_dependency(false, InteractionModel::Tray, false).is_err());
+        assert!(check_tray_dependency(false, InteractionModel::Hotkey, true).is_err());
+        // 有托盘时均可开启
+        assert!(check_tray_dependency(true, InteractionModel::Tray, true).is_ok());
+    }
+
+    #[test]
+    fn taskbar_only_while_visible_when_enabled() {
+        assert!(!should_skip_taskbar(true, true));
+        assert!(should_skip_taskbar(false, true));
+        assert!(should_skip_taskbar(true, false));
+        assert!(should_skip_taskbar(false, false));
+    }
+
+    #[test]
+    fn config_defaults_match_missing_fields() {
+        // 缺失字段由 #[serde(default)] 补齐，补齐的值应与前端拿到的默认值一致
+        let empty: AppConfig = serde_json::from_str("{}").unwrap();
+        assert_eq!(config_defaults(), empty);
+    }
+
+    #[test]
+    fn tour_steps_then_ends_and_wraps() {
+        let len = 3;
+        let mut step = 0;
+        let mut visited = Vec::new();
+        for _ in 0..len {
+            let (index, next) = advance_tour(len, step);
+            visited.push(index);
+            step = next;
+        }
+        assert_eq!(visited, vec![Some(0), Some(1), Some(2)]);
+
+        // 走完最后一步后结束一次，再从头开始
+        assert_eq!(advance_tour(len, step), (None, 0));
+        assert_eq!(advance_tour(len, 0), (Some(0), 1));
+        // 巡回被重新保存为更短的序列时同样结束
+        assert_eq!(advance_tour(1, 2), (None, 0));
+    }
+
+    #[test]
+    fn startup_report_lists_failed_subsystems() {
+        let report = build_startup_report(Ok(()), Ok(()), Ok(()));
+        assert!(report.window && report.tray && report.shortcut);
+        assert!(report.errors.is_empty());
+
+        let report = build_startup_report(Ok(()), Err("托盘不存在".to_string()), Err("已被占用".to_string()));
+        assert!(report.window);
+        assert!(!report.tray);
+        assert!(!report.shortcut);
+        assert_eq!(report.errors, vec!["tray: 托盘不存在".to_string(), "shortcut: 已被占用".to_string()]);
+
+        let report = build_startup_report(Err("创建失败".to_string()), Ok(()), Ok(()));
+        assert!(!report.window && report.tray && report.shortcut);
+        assert_eq!(report.errors, vec!["window: 创建失败".to_string()]);
+    }
+
+    #[test]
+    fn bubble_widens_within_monitor() {
+        assert_eq!(bubble_width(280.0, 120.0, 1000.0), 400.0);
+        // 不超出显示器内可用的宽度
+        assert_eq!(bubble_width(280.0, 120.0, 350.0), 350.0);
+        // 负数不会让窗口变窄
+        assert_eq!(bubble_width(280.0, -50.0, 1000.0), 280.0);
+        assert_eq!(bubble_width(280.0, 100.0, 200.0), 200.0);
+    }
+
+    #[test]
+    fn anchored_edge_stays_fixed_on_widen_and_restore() {
+        let pos = PhysicalPosition::new(1000, 50);
+        let base = PhysicalSize::new(280, 300);
+        let wide = PhysicalSize::new(400, 300);
+
+        assert_eq!(anchored_origin(Anchor::TopRight, pos, base, wide), PhysicalPosition::new(880, 50));
+        assert_eq!(anchored_origin(Anchor::TopLeft, pos, base, wide), pos);
+        assert_eq!(anchored_origin(Anchor::Center, pos, base, wide), PhysicalPosition::new(940, 50));
+        assert_eq!(
+            anchored_origin(Anchor::BottomRight, pos, base, PhysicalSize::new(400, 360)),
+            PhysicalPosition::new(880, -10)
+        );
+
+        // 收起后回到展开前的位置
+        let widened = anchored_origin(Anchor::TopRight, pos, base, wide);
+        assert_eq!(anchored_origin(Anchor::TopRight, widened, wide, base), pos);
+    }
+
+    #[test]
+    fn always_on_top_deferred_only_with_delay() {
+        // 0 表示显示时立即置顶，其余值延迟置顶
+        assert_eq!(always_on_top_delay(0), None);
+        assert_eq!(always_on_top_delay(1), Some(Duration::from_millis(1)));
+        assert_eq!(always_on_top_delay(250), Some(Duration::from_millis(250)));
+        assert_eq!(always_on_top_delay(AppConfig::default().always_on_top_delay_ms), None);
+    }
+
+    #[test]
+    fn toggle_history_is_capped_and_newest_first() {
+        let mut history = VecDeque::new();
+        for timestamp in 0..5 {
+            let event = ToggleEvent {
+                timestamp,
+                visible: timestamp % 2 == 0,
+            };
+            push_toggle_event(&mut history, event, 3);
+        }
+        assert_eq!(history.len(), 3);
+
+        let recent = recent_toggle_events(&history, 2);
+        assert_eq!(recent.iter().map(|e| e.timestamp).collect::<Vec<_>>(), vec![4, 3]);
+        assert!(recent[0].visible);
+        // limit 超出记录数时返回全部
+        assert_eq!(
+            recent_toggle_events(&history, 10).iter().map(|e| e.timestamp).collect::<Vec<_>>(),
+            vec![4, 3, 2]
+        );
+    }
+
+    #[test]
+    fn modifier_presets_are_valid() {
+        for preset in allowed_modifier_presets() {
+            assert!(!preset.is_empty());
+            assert!(!has_unknown_modifier(&preset), "{:?}", preset);
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn modifier_presets_use_mac_names() {
+        let presets = allowed_modifier_presets();
+        assert!(presets.contains(&vec!["Cmd".to_string(), "Option".to_string()]));
+        assert!(presets.iter().flatten().all(|m| m != "Alt"));
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    #[test]
+    fn modifier_presets_avoid_mac_keys() {
+        let presets = allowed_modifier_presets();
+        assert!(presets.contains(&vec!["Ctrl".to_string(), "Alt".to_string()]));
+        assert!(presets.iter().flatten().all(|m| m != "Cmd" && m != "Option"));
+    }
+
+    #[test]
+    fn nudge_moves_one_pixel_and_back() {
+        let position = PhysicalPosition::new(-300, 120);
+        let [nudged, restored] = nudge_positions(position);
+        assert_eq!(nudged, PhysicalPosition::new(-299, 120));
+        assert_eq!(restored, position);
+    }
+
+    #[test]
+    fn pinned_survives_save_and_reload() {
+        assert!(!AppConfig::default().pinned);
+
+        // 重启后恢复置顶，并按最长置顶时间重新计时
+        let config = AppConfig { pinned: true, max_pin_duration_secs: 60, ..AppConfig::default() };
+        let restored = round_trip("pinned", &config);
+        assert!(restored.pinned);
+        assert_eq!(
+            auto_unpin_delay(restored.pinned, restored.max_pin_duration_secs),
+            Some(Duration::from_secs(60))
+        );
+
+        // 未置顶时不计时
+        let restored = round_trip("unpinned", &AppConfig { max_pin_duration_secs: 60, ..AppConfig::default() });
+        assert_eq!(auto_unpin_delay(restored.pinned, restored.max_pin_duration_secs), None);
+    }
+
+    #[test]
+    fn peek_press_shows_and_release_hides() {
+        // 面板隐藏时按下显示，松开时隐藏
+        assert_eq!(peek_transition(ShortcutState::Pressed, false, false), Some(true));
+        assert_eq!(peek_transition(ShortcutState::Released, true, true), Some(false));
+        // 面板已打开时速览不改变显示状态
+        assert_eq!(peek_transition(ShortcutState::Pressed, true, false), None);
+        assert_eq!(peek_transition(ShortcutState::Released, true, false), None);
+        // 按住期间的重复按下事件不影响
+        assert_eq!(peek_transition(ShortcutState::Pressed, true, true), None);
+    }
+
+    #[test]
+    fn heartbeat_file_decides_crash() {
+        let now = 1_700_000_000;
+        let heartbeat = |timestamp: u64, clean_exit: bool| {
+            serde_json::to_string(&Heartbeat { timestamp, clean_exit }).unwrap()
+        };
+        assert!(previous_run_crashed(Some(&heartbeat(now - 30, false)), now));
+        assert!(!previous_run_crashed(Some(&heartbeat(now - 30, true)), now));
+        let stale = now - HEARTBEAT_MAX_AGE.as_secs() - 1;
+        assert!(!previous_run_crashed(Some(&heartbeat(stale, false)), now));
+        // 时钟回拨导致心跳时间在未来时按刚写入处理
+        assert!(previous_run_crashed(Some(&heartbeat(now + 60, false)), now));
+        // 首次运行或文件损坏
+        assert!(!previous_run_crashed(None, now));
+        assert!(!previous_run_crashed(Some("not json"), now));
+    }
+
+    #[test]
+    fn home_geometry_is_clamped_to_monitor() {
+        let pos = PhysicalPosition::new(0, 0);
+        let size = PhysicalSize::new(1920, 1080);
+
+        // 在显示器范围内的位置保持不变
+        let inside = Geometry { x: 100, y: 200, width: 300.0, height: 60.0 };
+        assert_eq!(clamp_geometry_to_monitor(&inside, pos, size, 1.0), inside);
+
+        // 超出右下角时贴边，超出左上角时贴到显示器原点
+        let outside = Geometry { x: 1900, y: 1070, width: 300.0, height: 60.0 };
+        let clamped = clamp_geometry_to_monitor(&outside, pos, size, 1.0);
+        assert_eq!((clamped.x, clamped.y), (1620, 1020));
+        assert_eq!((clamped.width, clamped.height), (300.0, 60.0));
+        let negative = Geometry { x: -50, y: -10, ..inside.clone() };
+        let clamped = clamp_geometry_to_monitor(&negative, pos, size, 1.0);
+        assert_eq!((clamped.x, clamped.y), (0, 0));
+
+        // 逻辑尺寸按缩放比例换算为物理像素
+        let clamped = clamp_geometry_to_monitor(&outside, pos, size, 2.0);
+        assert_eq!((clamped.x, clamped.y), (1320, 960));
+
+        // 副显示器的偏移也计入范围
+        let second = PhysicalPosition::new(1920, 0);
+        let clamped = clamp_geometry_to_monitor(&inside, second, size, 1.0);
+        assert_eq!((clamped.x, clamped.y), (1920, 200));
+    }
+
+    #[test]
+    fn home_restores_to_its_monitor_or_primary() {
+        let primary = (PhysicalPosition::new(0, 0), PhysicalSize::new(1920, 1080));
+        let second = (PhysicalPosition::new(1920, 0), PhysicalSize::new(2560, 1440));
+        let home = Geometry { x: 4000, y: 1300, width: 320.0, height: 80.0 };
+        let point = Some(PhysicalPosition::new(home.x as f64, home.y as f64));
+
+        // 原位所在的显示器仍连接时放回原处
+        assert_eq!(pick_monitor_index(&[primary, second], &[point]), Some(1));
+        assert_eq!(clamp_geometry_to_monitor(&home, second.0, second.1, 1.0), home);
+
+        // 显示器已断开时找不到原位所在的显示器，放到主显示器范围内
+        assert_eq!(pick_monitor_index(&[primary], &[point]), None);
+        let placed = clamp_geometry_to_monitor(&home, primary.0, primary.1, 1.0);
+        assert_eq!((placed.x, placed.y), (1600, 1000));
+        assert_eq!((placed.width, placed.height), (home.width, home.height));
+    }
+
+    #[test]
+    fn opacity_is_clamped() {
+        assert_eq!(clamp_opacity(0.0), 0.1);
+        assert_eq!(clamp_opacity(2.0), 1.0);
+        assert_eq!(clamp_opacity(0.5), 0.5);
+        assert_eq!(clamp_opacity(f64::NAN), 1.0);
+        assert_eq!(clamp_opacity(f64::INFINITY), 1.0);
+    }
+
+    #[test]
+    fn resize_border_is_clamped_on_set_and_load() {
+        assert_eq!(clamp_resize_border(0), MIN_RESIZE_BORDER);
+        assert_eq!(clamp_resize_border(100), MAX_RESIZE_BORDER);
+        assert_eq!(clamp_resize_border(4), 4);
+
+        // 手动修改配置文件写入的越界值在加载时被限制
+        let loaded = round_trip("resize-border", &AppConfig { resize_border_px: 100, ..AppConfig::default() });
+        assert_eq!(loaded.resize_border_px, MAX_RESIZE_BORDER);
+        let loaded = round_trip("resize-border-zero", &AppConfig { resize_border_px: 0, ..AppConfig::default() });
+        assert_eq!(loaded.resize_border_px, MIN_RESIZE_BORDER);
+    }
+
+    #[test]
+    fn unknown_layout_mode_falls_back_to_compact() {
+        assert_eq!(parse_layout_mode("dot"), LayoutMode::Dot);
+        assert_eq!(parse_layout_mode("compact"), LayoutMode::Compact);
+        assert_eq!(parse_layout_mode("grid"), LayoutMode::Compact);
+        assert_eq!(parse_layout_mode(""), LayoutMode::Compact);
+    }
+
+    #[test]
+    fn atomic_write_leaves_no_temp_file() {
+        let path = temp_config_path("atomic-write");
+        let config = AppConfig { opacity: 0.5, ..AppConfig::default() };
+        write_config_file(&path, &config).unwrap();
+
+        assert!(!path.with_extension("json.tmp").exists());
+        let content = fs::read_to_string(&path).unwrap();
+        let parsed: AppConfig = serde_json::from_str(&content).unwrap();
+        assert_eq!(parsed, config);
+    }
+
+    #[test]
+    fn dpi_change_keeps_logical_size() {
+        assert_eq!(scaled_physical_size(300.0, 60.0, 1.0), PhysicalSize::new(300, 60));
+        assert_eq!(scaled_physical_size(300.0, 60.0, 2.0), PhysicalSize::new(600, 120));
+        assert_eq!(scaled_physical_size(300.0, 60.0, 1.5), PhysicalSize::new(450, 90));
+        // 非整数结果四舍五入
+        assert_eq!(scaled_physical_size(301.0, 61.0, 1.25), PhysicalSize::new(376, 76));
+    }
+
+    #[test]
+    fn emergency_combo_maps_to_reset() {
+        let emergency = emergency_shortcut();
+        assert_eq!(emergency, Shortcut::new(Some(Modifiers::CONTROL | Modifiers::ALT | Modifiers::SHIFT), Code::KeyR));
+
+        let toggle = Shortcut::new(Some(Modifiers::ALT), Code::KeyM);
+        let registry = [(toggle, ShortcutAction::Toggle), (emergency, ShortcutAction::EmergencyReset)];
+        assert_eq!(find_action(&registry, &emergency), Some(ShortcutAction::EmergencyReset));
+    }
+
+    #[test]
+    fn emergency_reset_runs_outside_the_shortcut_lock() {
+        assert!(shortcut_fires(ShortcutAction::EmergencyReset, ShortcutState::Pressed));
+        assert!(!shortcut_fires(ShortcutAction::EmergencyReset, ShortcutState::Released));
+
+        // 模拟插件：回调期间持有快捷键表的锁，而重置需要重新注册快捷键，也要拿同一把锁
+        let shortcuts = std::sync::Arc::new(Mutex::new(Vec::<ShortcutAction>::new()));
+        let (done_tx, done_rx) = mpsc::channel();
+        let registry = shortcuts.clone();
+        let dispatcher = spawn_shortcut_dispatcher(move |action, state| {
+            if shortcut_fires(action, state) {
+                registry.lock().unwrap().push(action);
+                let _ = done_tx.send(action);
+            }
+        });
+
+        {
+            let _held = shortcuts.lock().unwrap();
+            dispatcher.send((ShortcutAction::EmergencyReset, ShortcutState::Pressed)).unwrap();
+            dispatcher.send((ShortcutAction::EmergencyReset, ShortcutState::Released)).unwrap();
+        }
+
+        // 回调返回后重置才执行，不会与插件的锁互相等待
+        assert_eq!(done_rx.recv_timeout(Duration::from_secs(5)), Ok(ShortcutAction::EmergencyReset));
+        assert_eq!(*shortcuts.lock().unwrap(), vec![ShortcutAction::EmergencyReset]);
+    }
+
+    static DISK_READS: AtomicUsize = AtomicUsize::new(0);
+
+    fn counting_reader(_path: &Path) -> AppConfig {
+        DISK_READS.fetch_add(1, Ordering::SeqCst);
+        AppConfig::default()
+    }
+
+    #[test]
+    fn mutators_read_from_cache() {
+        let cache = ConfigCache::new(counting_reader);
+        let path = temp_config_path("cache");
+
+        // 路径未初始化时不读磁盘
+        cache.load(None);
+        assert_eq!(DISK_READS.load(Ordering::SeqCst), 0);
+
+        // 首次加载读取一次，之后的修改和读取都走缓存
+        let mut config = cache.load(Some(path.clone()));
+        config.opacity = 0.5;
+        cache.set(&config);
+        assert_eq!(cache.load(Some(path.clone())).opacity, 0.5);
+        assert_eq!(DISK_READS.load(Ordering::SeqCst), 1);
+
+        // 显式重新加载才再次读取磁盘
+        assert_eq!(cache.reload(&path).opacity, AppConfig::default().opacity);
+        assert_eq!(DISK_READS.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn failed_shortcut_swap_restores_previous() {
+        let old = Shortcut::new(Some(Modifiers::ALT), Code::KeyM);
+        let new = Shortcut::new(Some(Modifiers::CONTROL), Code::KeyK);
+        let registered = std::cell::RefCell::new(vec![old]);
+        let unregister = |shortcut: Shortcut| registered.borrow_mut().retain(|s| *s != shortcut);
+
+        // 新快捷键被占用：原快捷键重新注册
+        let (current, result) = swap_shortcut(Some(old), new, unregister, |shortcut| {
+            if shortcut == new {
+                return Err("已被占用".to_string());
+            }
+            registered.borrow_mut().push(shortcut);
+            Ok(())
+        });
+        assert!(result.is_err());
+        assert_eq!(current, Some(old));
+        assert_eq!(*registered.borrow(), vec![old]);
+
+        // 注册成功：只保留新快捷键
+        let (current, result) = swap_shortcut(Some(old), new, unregister, |shortcut| {
+            registered.borrow_mut().push(shortcut);
+            Ok(())
+        });
+        assert!(result.is_ok());
+        assert_eq!(current, Some(new));
+        assert_eq!(*registered.borrow(), vec![new]);
+    }
+
+    #[test]
+    fn same_shortcut_ignores_aliases_and_order() {
+        let mods = |list: &[&str]| list.iter().map(|m| m.to_string()).collect::<Vec<_>>();
+        assert!(same_shortcut(&mods(&["Ctrl", "Shift"]), "K", &mods(&["Shift", "Control"]), "k"));
+        assert!(same_shortcut(&mods(&["Option"]), "M", &mods(&["Alt"]), "M"));
+        assert!(!same_shortcut(&mods(&["Ctrl"]), "K", &mods(&["Alt"]), "K"));
+        assert!(!same_shortcut(&mods(&["Ctrl"]), "K", &mods(&["Ctrl"]), "J"));
+        assert!(!same_shortcut(&mods(&["Ctrl"]), "K", &mods(&["Ctrl", "Shift"]), "K"));
+        // 无效按键即使两边相同也不算同一快捷键
+        assert!(!same_shortcut(&mods(&["Ctrl"]), "Nope", &mods(&["Ctrl"]), "Nope"));
+    }
+
+    #[test]
+    fn interaction_models_set_shortcuts_and_auto_hide() {
+        let hotkey = interaction_effects(InteractionModel::Hotkey);
+        assert!(hotkey.global_shortcuts);
+        assert!(hotkey.auto_hide);
+
+        let tray = interaction_effects(InteractionModel::Tray);
+        assert!(!tray.global_shortcuts);
+        assert!(!tray.auto_hide);
+
+        // 未知模式按快捷键模式处理
+        assert_eq!(interaction_effects(parse_interaction_model("unknown")), hotkey);
+        assert_eq!(interaction_effects(parse_interaction_model("tray")), tray);
+    }
+
+    #[test]
+    fn function_and_escape_keys_allow_no_modifier() {
+        assert!(key_allows_no_modifier("F5".to_string()));
+        assert!(key_allows_no_modifier("Escape".to_string()));
+        assert!(key_allows_no_modifier("Esc".to_string()));
+        assert!(!key_allows_no_modifier("M".to_string()));
+        assert!(!key_allows_no_modifier("Nope".to_string()));
+    }
+
+    #[test]
+    fn no_write_while_dragging() {
+        let interval = Duration::from_millis(500);
+        let last_move = Instant::now();
+        let last_write = Some(last_move - Duration::from_secs(5));
+
+        // 拖动中：写入间隔已满足，但窗口仍在移动
+        assert!(!should_flush(last_write, Some(last_move), last_move + Duration::from_millis(100), interval));
+        // 拖动结束：停止移动超过 MOVE_SETTLE 后写入
+        assert!(should_flush(last_write, Some(last_move), last_move + MOVE_SETTLE, interval));
+        // 从未拖动过时只受写入间隔限制
+        assert!(should_flush(last_write, None, last_move, interval));
+        assert!(!should_flush(Some(last_move), None, last_move + Duration::from_millis(100), interval));
+    }
+
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn macos_keeps_running_after_last_window_closes_by_default() {
+        assert!(!AppConfig::default().exit_on_last_window_close);
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    #[test]
+    fn exits_after_last_window_closes_by_default() {
+        assert!(AppConfig::default().exit_on_last_window_close);
+    }
+
+    #[test]
+    fn last_window_close_exit_follows_flag() {
+        assert!(prevent_exit(None, false));
+        assert!(!prevent_exit(None, true));
+        // 主动退出（带退出码）不受配置影响
+        assert!(!prevent_exit(Some(0), false));
+        assert!(!prevent_exit(Some(0), true));
+    }
+
+    #[test]
+    fn imported_shortcuts_are_checked_against_imported_config() {
+        let mods = |list: &[&str]| list.iter().map(|m| m.to_string()).collect::<Vec<_>>();
+        let base = AppConfig {
+            shortcut_modifiers: mods(&["Ctrl", "Alt", "Shift"]),
+            shortcut_key: "M".to_string(),
+            ..AppConfig::default()
+        };
+
+        // 修饰键上限取导入配置中的值
+        assert!(check_imported_shortcuts(&AppConfig { max_modifiers: Some(3), ..base.clone() }).is_ok());
+        assert!(check_imported_shortcuts(&AppConfig { max_modifiers: Some(2), ..base.clone() }).is_err());
+
+        // 可选快捷键与主快捷键重复
+        let duplicate = AppConfig {
+            pin_shortcut_modifiers: mods(&["Shift", "Control", "Alt"]),
+            pin_shortcut_key: "m".to_string(),
+            ..base.clone()
+        };
+        assert!(check_imported_shortcuts(&duplicate).is_err());
+
+        // 紧急重置组合不可使用
+        let emergency = AppConfig {
+            peek_shortcut_modifiers: mods(&["Ctrl", "Alt", "Shift"]),
+            peek_shortcut_key: "R".to_string(),
+            ..base.clone()
+        };
+        assert!(check_imported_shortcuts(&emergency).is_err());
+
+        // 未配置的可选快捷键跳过
+        let settings = AppConfig {
+            settings_shortcut_modifiers: mods(&["Ctrl"]),
+            settings_shortcut_key: "S".to_string(),
+            ..base
+        };
+        assert!(check_imported_shortcuts(&settings).is_ok());
+    }
+
+    #[test]
+    fn imported_bindings_are_validated_as_a_set() {
+        let mods = |list: &[&str]| list.iter().map(|m| m.to_string()).collect::<Vec<_>>();
+        let current = AppConfig {
+            shortcut_modifiers: mods(&["Alt"]),
+            shortcut_key: "M".to_string(),
+            pin_shortcut_modifiers: mods(&["Alt"]),
+            pin_shortcut_key: "P".to_string(),
+            ..AppConfig::default()
+        };
+
+        // 主快捷键与置顶快捷键互换组合
+        let swapped = with_shortcut_bindings(
+            &current,
+            vec![
+                ("toggle".to_string(), mods(&["Alt"]), "P".to_string()),
+                ("pin".to_string(), mods(&["Alt"]), "M".to_string()),
+            ],
+        );
+        assert_eq!(swapped.shortcut_key, "P");
+        assert_eq!(swapped.pin_shortcut_key, "M");
+        assert!(check_imported_shortcuts(&swapped).is_ok());
+
+        // 文件内部重复
+        let duplicate = with_shortcut_bindings(&current, vec![("peek".to_string(), mods(&["Alt"]), "M".to_string())]);
+        assert!(check_imported_shortcuts(&duplicate).is_err());
+
+        // 紧急重置组合
+        let emergency = with_shortcut_bindings(
+            &current,
+            vec![("settings".to_string(), mods(&["Ctrl", "Alt", "Shift"]), "R".to_string())],
+        );
+        assert!(check_imported_shortcuts(&emergency).is_err());
+    }
+
+    #[test]
+    fn drag_persists_only_when_enabled() {
+        // 用户拖动：按配置决定写入配置或只在本次运行中使用
+        assert_eq!(persist_move(false, false, true, true), Some(true));
+        assert_eq!(persist_move(false, false, true, false), Some(false));
+        // 程序主动移动、临时布局、窗口隐藏时忽略
+        assert_eq!(persist_move(true, false, true, true), None);
+        assert_eq!(persist_move(false, true, true, true), None);
+        assert_eq!(persist_move(false, false, false, true), None);
+
+        // 不保存时忽略配置中的旧位置，只用本次运行中的位置
+        let saved = AppConfig { x: Some(10), y: Some(20), ..AppConfig::default() };
+        assert_eq!(manual_position(&saved, Some((30, 40))), Some((10, 20)));
+        let session_only = AppConfig { persist_manual_position: false, ..saved };
+        assert_eq!(manual_position(&session_only, Some((30, 40))), Some((30, 40)));
+        assert_eq!(manual_position(&session_only, None), None);
+    }
+
+    #[test]
+    fn position_state_prefers_on_screen_manual_position() {
+        // 没有拖动后的位置时使用锚点
+        let state = build_position_state("top-right", None, false, (1600, 30));
+        assert_eq!(
+            state,
+            PositionState { anchor: "top-right".to_string(), manual_position: None, effective: (1600, 30) }
+        );
+
+        // 拖动后的位置覆盖锚点
+        let state = build_position_state("top-right", Some((100, 200)), true, (1600, 30));
+        assert_eq!(state.manual_position, Some((100, 200)));
+        assert_eq!(state.effective, (100, 200));
+
+        // 拖动后的位置不在任何显示器上时仍报告，但实际回到锚点
+        let state = build_position_state("center", Some((-5000, 200)), false, (800, 400));
+        assert_eq!(state.manual_position, Some((-5000, 200)));
+        assert_eq!(state.effective, (800, 400));
+    }
+
+    #[test]
+    fn shortcuts_fire_on_press_except_peek() {
+        assert!(shortcut_fires(ShortcutAction::Hide, ShortcutState::Pressed));
+        assert!(!shortcut_fires(ShortcutAction::Hide, ShortcutState::Released));
+        assert!(!shortcut_fires(ShortcutAction::Toggle, ShortcutState::Released));
+        // 速览松开时也需要处理
+        assert!(shortcut_fires(ShortcutAction::Peek, ShortcutState::Pressed));
+        assert!(shortcut_fires(ShortcutAction::Peek, ShortcutState::Released));
+    }
+
+    #[test]
+    fn save_supersedes_pending_config() {
+        let path = temp_config_path("supersede");
+        let pending = Mutex::new(Some(AppConfig { window_width: 400.0, ..AppConfig::default() }));
+
+        // 直接保存的新配置丢弃待写的旧配置，之后的合并写入不会覆盖
+        save_config_to(&pending, &path, &AppConfig { window_width: 800.0, ..AppConfig::default() }).unwrap();
+        flush_pending(&pending, &path);
+        assert_eq!(read_config_file(&path).window_width, 800.0);
+    }
+
+    #[test]
+    fn refresh_on_show_is_rate_limited() {
+        let now = Instant::now();
+        // 从未刷新或超过最小间隔时刷新
+        assert!(refresh_allowed(None, now));
+        assert!(refresh_allowed(Some(now), now + REFRESH_MIN_INTERVAL));
+        // 连续显示面板时不重复刷新
+        assert!(!refresh_allowed(Some(now), now + Duration::from_millis(500)));
+        // 默认不在显示时刷新
+        assert!(!AppConfig::default().refresh_on_show);
+    }
+
+    #[test]
+    fn refresh_combo_dispatches_to_refresh() {
+        let refresh = Shortcut::new(Some(Modifiers::CONTROL | Modifiers::SHIFT), Code::F5);
+        let toggle = Shortcut::new(Some(Modifiers::ALT), Code::KeyM);
+        let registry = [(toggle, ShortcutAction::Toggle), (refresh, ShortcutAction::Refresh)];
+
+        assert_eq!(find_action(&registry, &refresh), Some(ShortcutAction::Refresh));
+        assert!(shortcut_fires(ShortcutAction::Refresh, ShortcutState::Pressed));
+        assert!(!shortcut_fires(ShortcutAction::Refresh, ShortcutState::Released));
+    }
+
+    fn night_config(opacity: f64, night: Option<f64>, start: &str, end: &str) -> AppConfig {
+        AppConfig {
+            opacity,
+            night_opacity: night,
+            night_start: start.to_string(),
+            night_end: end.to_string(),
+            ..AppConfig::default()
+        }
+    }
+
+    #[test]
+    fn night_opacity_caps_inside_window_only() {
+        let config = night_config(0.9, Some(0.4), "08:00", "18:00");
+        assert_eq!(effective_opacity(&config, Some(12 * 60)), 0.4);
+        assert_eq!(effective_opacity(&config, Some(18 * 60)), 0.9);
+        assert_eq!(effective_opacity(&config, Some(7 * 60 + 59)), 0.9);
+        // 上限只会降低不透明度，用户本身更透明时保持不变
+        let dim = night_config(0.3, Some(0.4), "08:00", "18:00");
+        assert_eq!(effective_opacity(&dim, Some(12 * 60)), 0.3);
+        // 未启用或尚未收到本地时间时使用用户的不透明度
+        assert_eq!(effective_opacity(&night_config(0.9, None, "08:00", "18:00"), Some(12 * 60)), 0.9);
+        assert_eq!(effective_opacity(&config, None), 0.9);
+    }
+
+    #[test]
+    fn night_window_wraps_past_midnight() {
+        let config = night_config(1.0, Some(0.5), "22:00", "07:00");
+        assert_eq!(effective_opacity(&config, Some(23 * 60)), 0.5);
+        assert_eq!(effective_opacity(&config, Some(0)), 0.5);
+        assert_eq!(effective_opacity(&config, Some(6 * 60 + 59)), 0.5);
+        assert_eq!(effective_opacity(&config, Some(7 * 60)), 1.0);
+        assert_eq!(effective_opacity(&config, Some(21 * 60 + 59)), 1.0);
+        assert_eq!(parse_clock("24:00"), None);
+        assert_eq!(parse_clock("7:05"), Some(7 * 60 + 5));
+    }
+
+    #[test]
+    fn conflicts_are_checked_against_configured_bindings() {
+        let config = AppConfig {
+            shortcut_modifiers: vec!["Alt".to_string()],
+            shortcut_key: "M".to_string(),
+            pin_shortcut_modifiers: vec!["Ctrl".to_string(), "Shift".to_string()],
+            pin_shortcut_key: "P".to_string(),
+            interaction_model: "tray".to_string(),
+            ..AppConfig::default()
+        };
+        let pin = Shortcut::new(Some(Modifiers::CONTROL | Modifiers::SHIFT), Code::KeyP);
+        let toggle = Shortcut::new(Some(Modifiers::ALT), Code::KeyM);
+
+        // 仅托盘模式下没有注册任何快捷键，仍按配置拒绝重复绑定
+        assert!(check_shortcut_conflict(&pin, ShortcutAction::Refresh, &config).is_err());
+        assert!(check_shortcut_conflict(&toggle, ShortcutAction::Peek, &config).is_err());
+        assert!(check_shortcut_conflict(&emergency_shortcut(), ShortcutAction::Pin, &config).is_err());
+        // 重新绑定为自己当前的组合不算冲突
+        assert!(check_shortcut_conflict(&pin, ShortcutAction::Pin, &config).is_ok());
+        let free = Shortcut::new(Some(Modifiers::CONTROL), Code::F9);
+        assert!(check_shortcut_conflict(&free, ShortcutAction::Refresh, &config).is_ok());
+    }
+
+    #[test]
+    fn failed_registration_rolls_back_only_new_shortcuts() {
+        // 原本已注册的保留，本次新增的在失败时注销
+        let before = [ShortcutAction::Toggle];
+        let after = [ShortcutAction::Toggle, ShortcutAction::Settings, ShortcutAction::Pin];
+        assert_eq!(newly_registered(&before, &after), vec![ShortcutAction::Settings, ShortcutAction::Pin]);
+        assert!(newly_registered(&after, &after).is_empty());
+    }
 }
@@ -1,5 +1,6 @@
 import { useState, useEffect, useCallback, useRef, ReactNode } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { load } from "@tauri-apps/plugin-store";
 import { fetch } from "@tauri-apps/plugin-http";
 import {
@@ -662,6 +663,7 @@ interface SortableStockItemProps {
   trendData: number[];
   onDelete: (market: string, code: string) => void;
   getPriceClass: (change: number) => string;
+  formatNumber: (value: number) => string;
   formatPercent: (percent: number) => string;
   disabled: boolean;
 }
@@ -673,6 +675,7 @@ function SortableStockItem({
   trendData,
   onDelete,
   getPriceClass,
+  formatNumber,
   formatPercent,
   disabled,
 }: SortableStockItemProps) {
@@ -713,7 +716,7 @@ function SortableStockItem({
       </div>
       <div className="stock-price-info">
         <span className={`stock-price ${getPriceClass(stock.change)}`}>
-          {stock.price.toFixed(2)}
+          {formatNumber(stock.price)}
         </span>
         <span className={`stock-change ${getPriceClass(stock.change)}`}>
           {formatPercent(stock.changePercent)}
@@ -788,6 +791,36 @@ export function StockPanel() {
   const [isRecordingShortcut, setIsRecordingShortcut] = useState(false);
   const [recordedKeys, setRecordedKeys] = useState<{ modifiers: string[]; key: string; } | null>(null);
 
+  // 时间显示格式："24h" 或 "12h"
+  const [timeFormat, setTimeFormat] = useState("24h");
+  // 时间和数字格式化使用的区域设置
+  const [locale, setLocale] = useState("zh-CN");
+
+  // 加载时间格式和区域设置，修改后同步
+  useEffect(() => {
+    invoke<{ time_format: string; locale: string }>("get_config").then((config) => {
+      setTimeFormat(config.time_format);
+      setLocale(config.locale);
+    });
+    const unlistenTimeFormat = listen<string>("time-format", (event) => setTimeFormat(event.payload));
+    const unlistenLocale = listen<string>("locale", (event) => setLocale(event.payload));
+    return () => {
+      unlistenTimeFormat.then((fn) => fn());
+      unlistenLocale.then((fn) => fn());
+    };
+  }, []);
+
+  // 每分钟上报本地时间，后端据此进入或离开夜间不透明度时段
+  useEffect(() => {
+    const sync = () => {
+      const now = new Date();
+      invoke("sync_night_opacity", { hour: now.getHours(), minute: now.getMinutes() }).catch(() => {});
+    };
+    sync();
+    const timer = setInterval(sync, 60 * 1000);
+    return () => clearInterval(timer);
+  }, []);
+
   // 使用 ref 存储 configs，避免 refreshData 依赖 state 导致频繁重建
   const stockConfigsRef = useRef(stockConfigs);
   stockConfigsRef.current = stockConfigs;
@@ -798,9 +831,18 @@ export function StockPanel() {
       const data = await fetchStockData(stockConfigsRef.current);
       console.log("[refreshData] Received data:", data);
       if (data.length > 0) {
+        const now = new Date();
         setStocks(data);
-        setUpdateTime(new Date());
+        setUpdateTime(now);
         setError(null);
+        // 同步托盘提示中的价格和更新时间，托盘不可用时忽略
+        invoke("update_tray_tooltip", {
+          name: data[0].name,
+          price: data[0].price,
+          hour: now.getHours(),
+          minute: now.getMinutes(),
+          second: now.getSeconds(),
+        }).catch(() => {});
       } else {
         console.log("[refreshData] No data received, stocks not updated");
       }
@@ -812,6 +854,18 @@ export function StockPanel() {
     }
   }, []);
 
+  // 后端请求刷新（如显示面板时），没有自选股时不请求
+  useEffect(() => {
+    const unlisten = listen("refresh", () => {
+      if (stockConfigsRef.current.length > 0) {
+        refreshData();
+      }
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [refreshData]);
+
   useEffect(() => {
     refreshData();
     const timer = setInterval(() => {
@@ -960,10 +1014,11 @@ export function StockPanel() {
   };
 
   const formatTime = (date: Date) => {
-    return date.toLocaleTimeString("zh-CN", {
+    return date.toLocaleTimeString(locale, {
       hour: "2-digit",
       minute: "2-digit",
       second: "2-digit",
+      hour12: timeFormat === "12h",
     });
   };
 
@@ -973,9 +1028,14 @@ export function StockPanel() {
     return "stock-flat";
   };
 
+  // 按区域设置格式化数字（千位分隔符、小数点），保留两位小数
+  const formatNumber = (value: number) => {
+    return value.toLocaleString(locale, { minimumFractionDigits: 2, maximumFractionDigits: 2 });
+  };
+
   const formatPercent = (percent: number) => {
     const sign = percent >= 0 ? "+" : "";
-    return `${sign}${percent.toFixed(2)}%`;
+    return `${sign}${formatNumber(percent)}%`;
   };
 
   // 删除股票
@@ -1046,6 +1106,49 @@ export function StockPanel() {
     await invoke("set_pinned", { pinned: newPinned });
   };
 
+  // 加载置顶状态（重启后保持）
+  useEffect(() => {
+    invoke<boolean>("get_pinned").then(setIsPinned);
+    // 置顶快捷键切换后同步状态
+    const unlisten = listen<boolean>("pinned", (event) => setIsPinned(event.payload));
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  // 设置页快捷键触发后展开设置面板
+  useEffect(() => {
+    const unlisten = listen<{ route: string }>("navigate", (event) => {
+      if (event.payload.route === "settings") {
+        setShowSettings(true);
+      }
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  // 是否按 Esc 隐藏面板，修改后同步
+  const [escapeToHide, setEscapeToHide] = useState(false);
+  useEffect(() => {
+    invoke<{ escape_to_hide: boolean }>("get_config").then((config) => setEscapeToHide(config.escape_to_hide));
+    const unlisten = listen<boolean>("escape-to-hide", (event) => setEscapeToHide(event.payload));
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  // 开启 Esc 隐藏时，面板获得焦点时按 Esc 隐藏（录制快捷键时除外）
+  useEffect(() => {
+    const handleKeyDown = (e: KeyboardEvent) => {
+      if (e.key === "Escape" && escapeToHide && !isRecordingShortcut) {
+        invoke("hide_window");
+      }
+    };
+    window.addEventListener("keydown", handleKeyDown);
+    return () => window.removeEventListener("keydown", handleKeyDown);
+  }, [escapeToHide, isRecordingShortcut]);
+
   // 加载当前快捷键
   useEffect(() => {
     invoke<[string[], string]>("get_shortcut").then(([mods, key]) => {
@@ -1100,11 +1203,6 @@ export function StockPanel() {
       return;
     }
 
-    // 需要至少一个修饰键
-    if (modifiers.length === 0) {
-      return;
-    }
-
     // 转换按键码为按键名
     let keyName: string | null = null;
     if (code.startsWith("Key")) {
@@ -1124,12 +1222,27 @@ export function StockPanel() {
       keyName = "Escape";
     } else if (code === "Tab") {
       keyName = "Tab";
+    } else if (["ArrowUp", "ArrowDown", "ArrowLeft", "ArrowRight", "Backspace", "Delete", "Home", "End", "PageUp", "PageDown"].includes(code)) {
+      keyName = code;
+    } else if (/^Numpad(\d|Add|Subtract|Multiply|Divide|Enter)$/.test(code)) {
+      keyName = code;
     }
 
     if (!keyName) {
       return; // 不支持的按键
     }
 
+    // 功能键等可以单独使用，其余按键需要至少一个修饰键
+    if (modifiers.length === 0) {
+      const key = keyName;
+      invoke<boolean>("key_allows_no_modifier", { key }).then((allowed) => {
+        if (allowed) {
+          setRecordedKeys({ modifiers, key });
+        }
+      });
+      return;
+    }
+
     setRecordedKeys({ modifiers, key: keyName });
   };
 
@@ -1269,6 +1382,7 @@ export function StockPanel() {
                     trendData={trendData[stockKey] || []}
                     onDelete={handleDeleteStock}
                     getPriceClass={getPriceClass}
+                    formatNumber={formatNumber}
                     formatPercent={formatPercent}
                     disabled={sortOrder !== "none"}
                   />